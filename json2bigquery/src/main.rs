@@ -1,13 +1,108 @@
-use std::io;
+use std::{
+    env,
+    io::{self, BufRead},
+};
+
+use serde_json::value::RawValue;
+
+const USAGE: &str = "usage: json2bigquery [--separator SEPARATOR | --ndjson | --insert-into TABLE]";
 
 fn main() -> Result<(), serde_bigquery::Error> {
-    transcode(io::stdin(), io::stdout())
+    let mut args = env::args().skip(1);
+    match (args.next().as_deref(), args.next(), args.next()) {
+        (None, _, _) => transcode(io::stdin(), io::stdout()),
+        (Some("--ndjson"), None, _) => transcode_ndjson(io::stdin().lock(), io::stdout()),
+        (Some("--insert-into"), Some(table), None) => {
+            transcode_insert(io::stdin(), io::stdout(), &table)
+        }
+        (Some("--separator"), Some(separator), None) => {
+            transcode_separated(io::stdin(), io::stdout(), &separator)
+        }
+        _ => Err(serde_bigquery::Error::Message(USAGE.to_string())),
+    }
 }
 
-fn transcode<R: io::Read, W: io::Write>(reader: R, writer: W) -> Result<(), serde_bigquery::Error> {
-    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+fn transcode<R: io::Read, W: io::Write>(
+    mut reader: R,
+    writer: W,
+) -> Result<(), serde_bigquery::Error> {
+    let mut json = String::new();
+    reader.read_to_string(&mut json)?;
+    // parsed up front so malformed JSON can be told apart from values we can't serialize
+    let value: &RawValue = serde_json::from_str(&json).map_err(|err| {
+        serde_bigquery::Error::Message(format!(
+            "invalid JSON at byte {}: {}",
+            byte_offset(&json, err.line(), err.column()),
+            err
+        ))
+    })?;
+    let mut deserializer = serde_json::Deserializer::from_str(value.get());
     let mut serializer = serde_bigquery::Serializer::new(writer);
-    serde_transcode::transcode(&mut deserializer, &mut serializer)?;
+    serde_transcode::transcode(&mut deserializer, &mut serializer).map_err(|err| {
+        let path = serializer.current_path();
+        serde_bigquery::Error::Message(if path.is_empty() {
+            format!("can't serialize value: {}", err)
+        } else {
+            format!("can't serialize value at `{}`: {}", path, err)
+        })
+    })?;
+    Ok(())
+}
+
+/// Converts 1-based line and column reported by `serde_json` into a byte offset.
+fn byte_offset(json: &str, line: usize, column: usize) -> usize {
+    let preceding_lines: usize = json
+        .split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    preceding_lines + column.saturating_sub(1)
+}
+
+/// Transcodes a stream of JSON values (e.g. NDJSON) writing `separator` between the literals.
+fn transcode_separated<R: io::Read, W: io::Write>(
+    reader: R,
+    mut writer: W,
+    separator: &str,
+) -> Result<(), serde_bigquery::Error> {
+    let values = serde_json::Deserializer::from_reader(reader)
+        .into_iter::<Box<RawValue>>()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| serde_bigquery::Error::Message(err.to_string()))?;
+    let output = serde_bigquery::to_string_separated(&values, separator)?;
+    writer.write_all(output.as_bytes())?;
+    Ok(())
+}
+
+/// Transcodes newline-delimited JSON, one literal per line. Blank lines are skipped.
+fn transcode_ndjson<R: BufRead, W: io::Write>(
+    reader: R,
+    mut writer: W,
+) -> Result<(), serde_bigquery::Error> {
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        transcode(line.as_bytes(), &mut writer)
+            .map_err(|err| serde_bigquery::Error::Message(format!("line {}: {}", i + 1, err)))?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+/// Transcodes an array of objects into an `INSERT INTO table (...) VALUES (...),...;` statement.
+fn transcode_insert<R: io::Read, W: io::Write>(
+    reader: R,
+    mut writer: W,
+    table: &str,
+) -> Result<(), serde_bigquery::Error> {
+    let rows: Vec<serde_json::Map<String, serde_json::Value>> = serde_json::from_reader(reader)
+        .map_err(|err| {
+            serde_bigquery::Error::Message(format!("expected an array of objects: {}", err))
+        })?;
+    let statement = serde_bigquery::to_insert_into(table, &rows)?;
+    writeln!(writer, "{};", statement)?;
     Ok(())
 }
 
@@ -21,6 +116,77 @@ mod test {
         String::from_utf8(buf).unwrap()
     }
 
+    #[test]
+    fn test_malformed_json() {
+        let err = transcode("{\"a\": 1,\n \"b\": }".as_bytes(), io::sink()).unwrap_err();
+        assert!(
+            err.to_string().starts_with("invalid JSON at byte 15:"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_unsupported_value() {
+        let err = transcode("{\"a\": {\"b\": {}}}".as_bytes(), io::sink()).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("can't serialize value at `a.b`: empty struct"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_separator() {
+        let mut buf = Vec::new();
+        let json = "{\"a\": 1}\n[true, false]\n";
+        transcode_separated(json.as_bytes(), io::Cursor::new(&mut buf), ";\n").unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "STRUCT(1 AS `a`);\n[TRUE,FALSE]"
+        );
+    }
+
+    #[test]
+    fn test_ndjson() {
+        let mut buf = Vec::new();
+        let json = "{\"a\": 1}\n[true, false]\n\n\"x\"\n";
+        transcode_ndjson(json.as_bytes(), io::Cursor::new(&mut buf)).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "STRUCT(1 AS `a`)\n[TRUE,FALSE]\n\"x\"\n"
+        );
+
+        let err = transcode_ndjson("1\n2\n{\"a\": }\n".as_bytes(), io::sink()).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("line 3: invalid JSON at byte 6:"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_insert_into() {
+        let mut buf = Vec::new();
+        let json = "[{\"id\": 1, \"name\": \"a\"}, {\"name\": \"b\", \"id\": 2}]";
+        transcode_insert(
+            json.as_bytes(),
+            io::Cursor::new(&mut buf),
+            "mydataset.mytable",
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "INSERT INTO `mydataset.mytable` (`id`,`name`) VALUES (1,\"a\"),(2,\"b\");\n"
+        );
+
+        for json in &["{\"id\": 1}", "[1, 2]", "[]"] {
+            assert!(transcode_insert(json.as_bytes(), io::sink(), "t").is_err());
+        }
+    }
+
     #[test]
     fn test_simple_vals() {
         assert_eq!(to_bigquery("false"), "FALSE");
@@ -0,0 +1,46 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde::Serialize;
+use serde_bigquery::Serializer;
+
+fn bench_i64_slice(c: &mut Criterion) {
+    let v: Vec<i64> = (0..10_000).map(|i| i * 7919 - 5_000_000).collect();
+    let mut group = c.benchmark_group("i64");
+    group.bench_function("serialize_i64_slice", |b| {
+        b.iter(|| {
+            let mut serializer = Serializer::new(Vec::new());
+            serializer.serialize_i64_slice(black_box(&v)).unwrap();
+            serializer
+        })
+    });
+    group.bench_function("seq", |b| {
+        b.iter(|| {
+            let mut serializer = Serializer::new(Vec::new());
+            black_box(&v).serialize(&mut serializer).unwrap();
+            serializer
+        })
+    });
+    group.finish();
+}
+
+fn bench_f64_slice(c: &mut Criterion) {
+    let v: Vec<f64> = (0..10_000).map(|i| i as f64 * 0.37 - 1000.0).collect();
+    let mut group = c.benchmark_group("f64");
+    group.bench_function("serialize_f64_slice", |b| {
+        b.iter(|| {
+            let mut serializer = Serializer::new(Vec::new());
+            serializer.serialize_f64_slice(black_box(&v)).unwrap();
+            serializer
+        })
+    });
+    group.bench_function("seq", |b| {
+        b.iter(|| {
+            let mut serializer = Serializer::new(Vec::new());
+            black_box(&v).serialize(&mut serializer).unwrap();
+            serializer
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_i64_slice, bench_f64_slice);
+criterion_main!(benches);
@@ -0,0 +1,331 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use crate::ser::identifier::{suffixed_identifier, CaseStyle};
+use crate::types::{Field, Type};
+
+/// Name of the outermost generated item.
+const ROOT_NAME: &str = "Generated";
+
+/// Keywords and reserved words, written as raw identifiers.
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+/// Keywords that can't be raw identifiers either.
+const RESERVED_IDENTIFIERS: &[&str] = &["_", "crate", "self", "super"];
+
+impl Type {
+    /// Renders Rust definitions of a type that serializes as `self`, as a starting point for
+    /// hand written structs. `STRUCT<a INT64, b ARRAY<STRING>>` becomes:
+    ///
+    /// ```text
+    /// #[derive(Serialize)]
+    /// struct Generated {
+    ///     a: i64,
+    ///     b: Vec<String>,
+    /// }
+    /// ```
+    ///
+    /// Nested structs get their own definitions named after the fields holding them
+    /// (`GeneratedAddress`, numbered if another struct has the name already), a type other than
+    /// a struct is aliased as `type Generated = ...;`.
+    /// Field names are converted to snake case (renamed back with `#[serde(rename)]`) and
+    /// `nullable` fields are wrapped in `Option`. Types without a plain Rust counterpart
+    /// (`NUMERIC`, `DATE`, `JSON`, ...) become `String`, an unresolved type becomes `Option<()>`.
+    pub fn to_rust_type_string(&self) -> String {
+        let mut definitions = Definitions::default();
+        match self {
+            Type::Struct(fields) => {
+                define_struct(ROOT_NAME, fields, &mut definitions);
+            }
+            _ => {
+                definitions.items.push(String::new());
+                definitions.names.insert(ROOT_NAME.to_string());
+                let element_name = format!("{}Element", ROOT_NAME);
+                let expression = rust_type(self, &element_name, &mut definitions);
+                definitions.items[0] = format!("type {} = {};\n", ROOT_NAME, expression);
+            }
+        }
+        definitions.items.join("\n")
+    }
+}
+
+/// Generated items and the type names they took.
+#[derive(Default)]
+struct Definitions {
+    items: Vec<String>,
+    names: HashSet<String>,
+}
+
+/// Type expression for `t`, structs are named after `name` and defined in `definitions`.
+fn rust_type(t: &Type, name: &str, definitions: &mut Definitions) -> String {
+    match t {
+        Type::Any => "Option<()>".to_string(),
+        Type::Bool => "bool".to_string(),
+        Type::Int64 => "i64".to_string(),
+        Type::Float64 => "f64".to_string(),
+        // a plain `Vec<u8>` would be written as `ARRAY<INT64>`
+        Type::Bytes(_) => "serde_bytes::ByteBuf".to_string(),
+        Type::Struct(fields) => define_struct(name, fields, definitions),
+        Type::Array(element_type) => format!("Vec<{}>", rust_type(element_type, name, definitions)),
+        _ => "String".to_string(),
+    }
+}
+
+/// Appends the definition of a struct, followed by the ones of the structs nested in it, and
+/// returns its name: `name` or, if another struct took it already, `name` with a number.
+fn define_struct(name: &str, fields: &[Field], definitions: &mut Definitions) -> String {
+    let name = (1..)
+        .map(|n| match n {
+            1 => name.to_string(),
+            n => format!("{}{}", name, n),
+        })
+        .find(|candidate| !definitions.names.contains(candidate))
+        .unwrap();
+    definitions.names.insert(name.clone());
+    let index = definitions.items.len();
+    definitions.items.push(String::new());
+
+    let mut definition = format!("#[derive(Serialize)]\nstruct {}", name);
+    if fields.iter().all(|field| field.field_name.is_none()) {
+        // written as a tuple
+        let types: Vec<_> = fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| field_type(field, &format!("{}{}", name, i), definitions))
+            .collect();
+        let _ = writeln!(definition, "({});", types.join(", "));
+    } else {
+        definition.push_str(" {\n");
+        let mut taken = HashSet::new();
+        for (i, field) in fields.iter().enumerate() {
+            let field_name = field
+                .field_name
+                .clone()
+                .unwrap_or_else(|| format!("field_{}", i));
+            let mut rust_name = field_identifier(&field_name, i);
+            // names colliding after the conversion (`userName`, `user_name`) get suffixed
+            if taken.contains(&rust_name) {
+                rust_name = suffixed_identifier(rust_name.trim_start_matches("r#"), &taken);
+            }
+            taken.insert(rust_name.clone());
+            let nested_name = format!(
+                "{}{}",
+                name,
+                pascal_case(rust_name.trim_start_matches("r#"))
+            );
+            if rust_name.trim_start_matches("r#") != field_name {
+                let _ = writeln!(definition, "    #[serde(rename = {:?})]", field_name);
+            }
+            let _ = writeln!(
+                definition,
+                "    {}: {},",
+                rust_name,
+                field_type(field, &nested_name, definitions)
+            );
+        }
+        definition.push_str("}\n");
+    }
+    definitions.items[index] = definition;
+    name
+}
+
+fn field_type(field: &Field, name: &str, definitions: &mut Definitions) -> String {
+    let rust_type = rust_type(&field.field_type, name, definitions);
+    if field.nullable && field.field_type != Type::Any {
+        format!("Option<{}>", rust_type)
+    } else {
+        rust_type
+    }
+}
+
+/// Snake case version of a field name usable as a Rust identifier.
+fn field_identifier(field_name: &str, position: usize) -> String {
+    let name = CaseStyle::SnakeCase.apply(field_name);
+    if name.is_empty()
+        || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        || RESERVED_IDENTIFIERS.contains(&name.as_str())
+    {
+        format!("field_{}", position)
+    } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else if RUST_KEYWORDS.contains(&name.as_str()) {
+        format!("r#{}", name)
+    } else {
+        name
+    }
+}
+
+fn pascal_case(name: &str) -> String {
+    let mut chars = CaseStyle::CamelCase.apply(name).chars().collect::<Vec<_>>();
+    if let Some(first) = chars.first_mut() {
+        *first = first.to_ascii_uppercase();
+    }
+    chars.into_iter().collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::field;
+
+    #[test]
+    fn test_nested_struct() {
+        let nullable_zip = field("zip", Type::Int64).with_nullable(true);
+        let t = Type::Struct(vec![
+            field("id", Type::Int64),
+            field("userName", Type::String(None)),
+            field("type", Type::Bool),
+            field(
+                "addresses",
+                Type::Array(Box::new(Type::Struct(vec![
+                    field("city", Type::String(None)),
+                    nullable_zip,
+                    field("location", Type::Struct(vec![field("lat", Type::Float64)])),
+                ]))),
+            ),
+            field(
+                "pair",
+                Type::Struct(vec![
+                    Field::with_type_and_name(Type::Numeric(None), None),
+                    Field::with_type_and_name(Type::Bytes(None), None),
+                ]),
+            ),
+        ]);
+        assert_eq!(
+            t.to_rust_type_string(),
+            r#"#[derive(Serialize)]
+struct Generated {
+    id: i64,
+    #[serde(rename = "userName")]
+    user_name: String,
+    r#type: bool,
+    addresses: Vec<GeneratedAddresses>,
+    pair: GeneratedPair,
+}
+
+#[derive(Serialize)]
+struct GeneratedAddresses {
+    city: String,
+    zip: Option<i64>,
+    location: GeneratedAddressesLocation,
+}
+
+#[derive(Serialize)]
+struct GeneratedAddressesLocation {
+    lat: f64,
+}
+
+#[derive(Serialize)]
+struct GeneratedPair(String, serde_bytes::ByteBuf);
+"#
+        );
+    }
+
+    #[test]
+    fn test_field_identifiers() {
+        let t = Type::Struct(vec![
+            field("self", Type::Int64),
+            field("crate", Type::Int64),
+            field("_", Type::Int64),
+            field("yield", Type::Int64),
+            field("gen", Type::Int64),
+            field("userName", Type::Int64),
+            field("user_name", Type::Int64),
+            field("user_name_2", Type::Int64),
+            field("Type", Type::Int64),
+            field("type", Type::Struct(vec![field("a", Type::Bool)])),
+            field("field_1", Type::Int64),
+        ]);
+        assert_eq!(
+            t.to_rust_type_string(),
+            r#"#[derive(Serialize)]
+struct Generated {
+    #[serde(rename = "self")]
+    field_0: i64,
+    #[serde(rename = "crate")]
+    field_1: i64,
+    #[serde(rename = "_")]
+    field_2: i64,
+    r#yield: i64,
+    r#gen: i64,
+    #[serde(rename = "userName")]
+    user_name: i64,
+    #[serde(rename = "user_name")]
+    user_name_2: i64,
+    #[serde(rename = "user_name_2")]
+    user_name_2_2: i64,
+    #[serde(rename = "Type")]
+    r#type: i64,
+    #[serde(rename = "type")]
+    type_2: GeneratedType2,
+    #[serde(rename = "field_1")]
+    field_1_2: i64,
+}
+
+#[derive(Serialize)]
+struct GeneratedType2 {
+    a: bool,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_struct_name_collisions() {
+        let t = Type::Struct(vec![
+            field(
+                "addresses_location",
+                Type::Struct(vec![field("a", Type::Bool)]),
+            ),
+            field(
+                "addresses",
+                Type::Struct(vec![field(
+                    "location",
+                    Type::Struct(vec![field("b", Type::Int64)]),
+                )]),
+            ),
+        ]);
+        assert_eq!(
+            t.to_rust_type_string(),
+            r#"#[derive(Serialize)]
+struct Generated {
+    addresses_location: GeneratedAddressesLocation,
+    addresses: GeneratedAddresses,
+}
+
+#[derive(Serialize)]
+struct GeneratedAddressesLocation {
+    a: bool,
+}
+
+#[derive(Serialize)]
+struct GeneratedAddresses {
+    location: GeneratedAddressesLocation2,
+}
+
+#[derive(Serialize)]
+struct GeneratedAddressesLocation2 {
+    b: i64,
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_type_alias() {
+        assert_eq!(
+            Type::Array(Box::new(Type::Int64)).to_rust_type_string(),
+            "type Generated = Vec<i64>;\n"
+        );
+        assert_eq!(
+            Type::Array(Box::new(Type::Struct(vec![field("a", Type::Any)]))).to_rust_type_string(),
+            "type Generated = Vec<GeneratedElement>;\n\n#[derive(Serialize)]\nstruct GeneratedElement {\n    a: Option<()>,\n}\n"
+        );
+    }
+}
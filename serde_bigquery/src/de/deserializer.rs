@@ -0,0 +1,194 @@
+use serde::de::{
+    self,
+    value::{MapDeserializer, SeqDeserializer},
+    DeserializeOwned, IntoDeserializer, Visitor,
+};
+
+use crate::de::parser::{parse, Literal};
+use crate::error::{Error, Result};
+
+/// Deserialize an instance of `T` from BigQuery literal syntax.
+///
+/// Only the subset written by `Serializer` is understood: `NULL`, booleans, numbers, quoted
+/// strings and bytes, arrays and structs. Struct fields are matched by their `AS` names,
+/// structs without names (tuples, typed structs) deserialize as sequences. Typed literals like
+/// `DATE '2024-01-02'` or `JSON '...'` deserialize as their string.
+///
+/// Values are parsed before they're deserialized so nothing can borrow from `s`.
+pub fn from_str<T>(s: &str) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    T::deserialize(parse(s)?)
+}
+
+fn visit_seq<'de, V: Visitor<'de>>(elements: Vec<Literal>, visitor: V) -> Result<V::Value> {
+    let mut seq = SeqDeserializer::new(elements.into_iter());
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Literal {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Literal {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Literal::Null => visitor.visit_unit(),
+            Literal::Bool(v) => visitor.visit_bool(v),
+            Literal::Int64(v) => visitor.visit_i64(v),
+            Literal::UInt64(v) => visitor.visit_u64(v),
+            Literal::Float64(v) => visitor.visit_f64(v),
+            Literal::String(v) => visitor.visit_string(v),
+            Literal::Bytes(v) => visitor.visit_byte_buf(v),
+            Literal::Array(elements) => visit_seq(elements, visitor),
+            Literal::Struct(fields) => {
+                if fields.iter().all(|(name, _)| name.is_some()) {
+                    let mut map = MapDeserializer::new(
+                        fields
+                            .into_iter()
+                            .map(|(name, value)| (name.unwrap_or_default(), value)),
+                    );
+                    let value = visitor.visit_map(&mut map)?;
+                    map.end()?;
+                    Ok(value)
+                } else {
+                    visit_seq(
+                        fields.into_iter().map(|(_, value)| value).collect(),
+                        visitor,
+                    )
+                }
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Literal::Null => visitor.visit_none(),
+            literal => visitor.visit_some(literal),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        // only unit variants are written (as strings)
+        match self {
+            Literal::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            _ => Err(de::Error::custom("expected enum variant name")),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ser::to_string;
+    use serde_derive::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    enum Status {
+        Active,
+        Closed,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Address {
+        city: String,
+        zip: Option<u32>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Account {
+        id: u64,
+        name: String,
+        balance: f64,
+        verified: bool,
+        status: Status,
+        tags: Vec<String>,
+        address: Address,
+        pair: (i32, String),
+        #[serde(with = "serde_bytes")]
+        key: Vec<u8>,
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let account = Account {
+            id: 42,
+            name: "Ada".to_string(),
+            balance: -12.5,
+            verified: true,
+            status: Status::Closed,
+            tags: vec!["a".to_string(), "b".to_string()],
+            address: Address {
+                city: "London".to_string(),
+                zip: None,
+            },
+            pair: (7, "seven".to_string()),
+            key: vec![0, 1, 255],
+        };
+        let serialized = to_string(&account).unwrap();
+        assert_eq!(from_str::<Account>(&serialized).unwrap(), account);
+
+        let statuses = vec![Status::Active, Status::Closed];
+        assert_eq!(
+            from_str::<Vec<Status>>(&to_string(&statuses).unwrap()).unwrap(),
+            statuses
+        );
+    }
+
+    #[test]
+    fn test_fields_matched_by_name() {
+        assert_eq!(
+            from_str::<Address>("STRUCT(NULL AS `zip`,\"Paris\" AS `city`)").unwrap(),
+            Address {
+                city: "Paris".to_string(),
+                zip: None
+            }
+        );
+        assert!(from_str::<Address>("STRUCT(\"Paris\" AS `town`)").is_err());
+    }
+
+    #[test]
+    fn test_nonfinite_floats() {
+        let values = vec![f64::INFINITY, 1.5];
+        assert_eq!(
+            from_str::<Vec<f64>>(&to_string(&values).unwrap()).unwrap(),
+            values
+        );
+    }
+
+    #[test]
+    fn test_deep_nesting() {
+        // fails instead of overflowing the stack
+        assert!(matches!(
+            from_str::<Vec<u32>>(&"[".repeat(200_000)),
+            Err(Error::Syntax { .. })
+        ));
+    }
+}
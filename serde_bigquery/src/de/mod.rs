@@ -0,0 +1,4 @@
+mod deserializer;
+mod parser;
+
+pub use deserializer::from_str;
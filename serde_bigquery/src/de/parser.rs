@@ -0,0 +1,496 @@
+use std::convert::TryFrom;
+
+use crate::error::{Error, Result};
+
+/// Literal parsed from BigQuery syntax, the subset written by `Serializer`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Literal {
+    Null,
+    Bool(bool),
+    Int64(i64),
+    /// Integer that only fits in `u64`.
+    UInt64(u64),
+    Float64(f64),
+    String(String),
+    Bytes(Vec<u8>),
+    Array(Vec<Literal>),
+    /// Struct fields with their `AS` names, `VALUES` rows are parsed as unnamed structs.
+    Struct(Vec<(Option<String>, Literal)>),
+}
+
+/// Keywords of typed literals like `DATE '2024-01-02'`, their values are parsed as strings.
+const TYPED_STRING_PREFIXES: &[&str] = &[
+    "JSON",
+    "NUMERIC",
+    "BIGNUMERIC",
+    "DATE",
+    "DATETIME",
+    "TIMESTAMP",
+];
+
+/// Arrays and structs nested deeper than this are rejected instead of overflowing the stack,
+/// like in serde_json.
+const MAX_DEPTH: usize = 128;
+
+pub(crate) fn parse(input: &str) -> Result<Literal> {
+    let mut parser = Parser {
+        input,
+        position: 0,
+        remaining_depth: MAX_DEPTH,
+    };
+    let literal = parser.literal()?;
+    parser.skip_whitespace();
+    if parser.position < input.len() {
+        return Err(parser.error("end of input"));
+    }
+    Ok(literal)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+    remaining_depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, expected: &'static str) -> Error {
+        Error::Syntax {
+            position: self.position,
+            expected,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skips whitespace and consumes `token` if it's next.
+    fn eat(&mut self, token: u8) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: u8, expected: &'static str) -> Result<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(expected))
+        }
+    }
+
+    /// Consumes a run of identifier characters (possibly empty).
+    fn word(&mut self) -> &'a str {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest
+            .bytes()
+            .take_while(|b| b.is_ascii_alphanumeric() || *b == b'_')
+            .count();
+        self.position += len;
+        &rest[..len]
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let position = self.position;
+        if self.word().eq_ignore_ascii_case(keyword) {
+            true
+        } else {
+            self.position = position;
+            false
+        }
+    }
+
+    /// Runs `f` parsing a nested array or struct one level deeper.
+    fn nested<F>(&mut self, f: F) -> Result<Literal>
+    where
+        F: FnOnce(&mut Self) -> Result<Literal>,
+    {
+        if self.remaining_depth == 0 {
+            return Err(self.error("at most 128 levels of nesting"));
+        }
+        self.remaining_depth -= 1;
+        let literal = f(self);
+        self.remaining_depth += 1;
+        literal
+    }
+
+    fn literal(&mut self) -> Result<Literal> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'[') => self.nested(Self::array),
+            Some(b'(') => self.nested(Self::fields),
+            Some(b'"' | b'\'') => self.string(),
+            Some(b'-' | b'+' | b'.' | b'0'..=b'9') => self.number(),
+            _ => {
+                let word = self.word();
+                match word.to_ascii_uppercase().as_str() {
+                    "NULL" => Ok(Literal::Null),
+                    "TRUE" => Ok(Literal::Bool(true)),
+                    "FALSE" => Ok(Literal::Bool(false)),
+                    "B" if matches!(self.peek(), Some(b'"' | b'\'')) => {
+                        self.quoted().map(Literal::Bytes)
+                    }
+                    "STRUCT" => {
+                        self.skip_type_parameters()?;
+                        self.nested(Self::fields)
+                    }
+                    "ARRAY" => {
+                        self.skip_type_parameters()?;
+                        self.nested(Self::array)
+                    }
+                    "CAST" => self.nested(Self::cast),
+                    "PARSE_JSON" | "ST_GEOGFROMTEXT" => {
+                        self.expect(b'(', "`(`")?;
+                        let json = self.string()?;
+                        self.expect(b')', "`)`")?;
+                        Ok(json)
+                    }
+                    "INTERVAL" => {
+                        self.skip_whitespace();
+                        let body = self.string()?;
+                        self.word();
+                        if !self.keyword("TO") {
+                            return Err(self.error("`TO`"));
+                        }
+                        self.word();
+                        Ok(body)
+                    }
+                    w if TYPED_STRING_PREFIXES.contains(&w) => {
+                        self.skip_whitespace();
+                        self.string()
+                    }
+                    _ => {
+                        self.position -= word.len();
+                        Err(self.error("literal"))
+                    }
+                }
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Literal> {
+        self.expect(b'[', "`[`")?;
+        let mut elements = Vec::new();
+        if self.eat(b']') {
+            return Ok(Literal::Array(elements));
+        }
+        loop {
+            elements.push(self.literal()?);
+            if !self.eat(b',') {
+                self.expect(b']', "`,` or `]`")?;
+                return Ok(Literal::Array(elements));
+            }
+        }
+    }
+
+    /// Parses `(value AS name, ...)` of a struct or a `VALUES` row.
+    fn fields(&mut self) -> Result<Literal> {
+        self.expect(b'(', "`(`")?;
+        let mut fields = Vec::new();
+        if self.eat(b')') {
+            return Ok(Literal::Struct(fields));
+        }
+        loop {
+            let value = self.literal()?;
+            let name = if self.keyword("AS") {
+                Some(self.identifier()?)
+            } else {
+                None
+            };
+            fields.push((name, value));
+            if !self.eat(b',') {
+                self.expect(b')', "`,` or `)`")?;
+                return Ok(Literal::Struct(fields));
+            }
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        if self.peek() == Some(b'`') {
+            self.quoted_string()
+        } else {
+            match self.word() {
+                "" => Err(self.error("identifier")),
+                word => Ok(word.to_string()),
+            }
+        }
+    }
+
+    /// Skips `<...>` after `STRUCT` or `ARRAY` if present.
+    fn skip_type_parameters(&mut self) -> Result<()> {
+        if !self.eat(b'<') {
+            return Ok(());
+        }
+        let mut depth = 1;
+        while depth > 0 {
+            match self.peek() {
+                Some(b'<') => depth += 1,
+                Some(b'>') => depth -= 1,
+                Some(b'`') => {
+                    self.identifier()?;
+                    continue;
+                }
+                Some(_) => {}
+                None => return Err(self.error("`>`")),
+            }
+            self.position += 1;
+        }
+        Ok(())
+    }
+
+    /// Parses `CAST(value AS type)`, the value is returned as it is except for string
+    /// representations of non-finite `FLOAT64` values.
+    fn cast(&mut self) -> Result<Literal> {
+        self.expect(b'(', "`(`")?;
+        let value = self.literal()?;
+        if !self.keyword("AS") {
+            return Err(self.error("`AS`"));
+        }
+        let cast_type = self.word().to_ascii_uppercase();
+        self.skip_type_parameters()?;
+        self.expect(b')', "`)`")?;
+        match value {
+            Literal::String(s) if cast_type == "FLOAT64" => s
+                .parse()
+                .map(Literal::Float64)
+                .map_err(|_| self.error("float")),
+            value => Ok(value),
+        }
+    }
+
+    fn number(&mut self) -> Result<Literal> {
+        let rest = self.rest();
+        let bytes = rest.as_bytes();
+        let mut len = 0;
+        let mut integer = true;
+        if matches!(bytes.first(), Some(b'-' | b'+')) {
+            len += 1;
+        }
+        while let Some(&b) = bytes.get(len) {
+            match b {
+                b'0'..=b'9' => {}
+                b'.' | b'e' | b'E' => integer = false,
+                b'-' | b'+' if matches!(bytes[len - 1], b'e' | b'E') => {}
+                _ => break,
+            }
+            len += 1;
+        }
+        let number = &rest[..len];
+        let literal = if integer {
+            number
+                .parse()
+                .map(Literal::Int64)
+                .or_else(|_| number.parse().map(Literal::UInt64))
+                .or_else(|_| number.parse().map(Literal::Float64))
+                .ok()
+        } else {
+            number.parse().map(Literal::Float64).ok()
+        };
+        let literal = literal.ok_or_else(|| self.error("number"))?;
+        self.position += len;
+        Ok(literal)
+    }
+
+    fn string(&mut self) -> Result<Literal> {
+        self.quoted_string().map(Literal::String)
+    }
+
+    fn quoted_string(&mut self) -> Result<String> {
+        let position = self.position;
+        let bytes = self.quoted()?;
+        String::from_utf8(bytes).map_err(|_| {
+            self.position = position;
+            self.error("UTF-8 string")
+        })
+    }
+
+    /// Parses a single, double or backtick quoted string with escape sequences.
+    ///
+    /// https://cloud.google.com/bigquery/docs/reference/standard-sql/lexical#escape_sequences
+    fn quoted(&mut self) -> Result<Vec<u8>> {
+        let quote = match self.peek() {
+            Some(q @ (b'"' | b'\'' | b'`')) => q,
+            _ => return Err(self.error("string")),
+        };
+        self.position += 1;
+        let mut result = Vec::new();
+        loop {
+            let c = self
+                .rest()
+                .chars()
+                .next()
+                .ok_or_else(|| self.error("closing quote"))?;
+            self.position += c.len_utf8();
+            match c {
+                c if c as u32 == u32::from(quote) => return Ok(result),
+                '\\' => self.escape(&mut result)?,
+                c => {
+                    let mut buffer = [0; 4];
+                    result.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+                }
+            }
+        }
+    }
+
+    fn escape(&mut self, result: &mut Vec<u8>) -> Result<()> {
+        let c = self.peek().ok_or_else(|| self.error("escape sequence"))?;
+        self.position += 1;
+        let simple = match c {
+            b'a' => Some(0x07),
+            b'b' => Some(0x08),
+            b'f' => Some(0x0c),
+            b'n' => Some(b'\n'),
+            b'r' => Some(b'\r'),
+            b't' => Some(b'\t'),
+            b'v' => Some(0x0b),
+            b'\\' | b'?' | b'"' | b'\'' | b'`' => Some(c),
+            _ => None,
+        };
+        if let Some(b) = simple {
+            result.push(b);
+            return Ok(());
+        }
+        let (digits, radix) = match c {
+            b'x' | b'X' => (2, 16),
+            b'u' => (4, 16),
+            b'U' => (8, 16),
+            b'0'..=b'7' => {
+                self.position -= 1;
+                (3, 8)
+            }
+            _ => return Err(self.error("escape sequence")),
+        };
+        let code = self
+            .rest()
+            .get(..digits)
+            .and_then(|s| u32::from_str_radix(s, radix).ok())
+            .ok_or_else(|| self.error("escape sequence"))?;
+        self.position += digits;
+        if matches!(c, b'u' | b'U') {
+            let c = char::from_u32(code).ok_or_else(|| self.error("unicode code point"))?;
+            let mut buffer = [0; 4];
+            result.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+        } else {
+            result.push(u8::try_from(code).map_err(|_| self.error("escape sequence"))?);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_scalars() {
+        assert_eq!(parse("NULL").unwrap(), Literal::Null);
+        assert_eq!(parse(" true ").unwrap(), Literal::Bool(true));
+        assert_eq!(parse("-12").unwrap(), Literal::Int64(-12));
+        assert_eq!(
+            parse("18446744073709551615").unwrap(),
+            Literal::UInt64(u64::MAX)
+        );
+        assert_eq!(parse("1.5e-3").unwrap(), Literal::Float64(0.0015));
+        assert!(matches!(
+            parse("CAST('NaN' AS FLOAT64)").unwrap(),
+            Literal::Float64(f) if f.is_nan()
+        ));
+        assert_eq!(
+            parse(r#""a\"b\n\x41é""#).unwrap(),
+            Literal::String("a\"b\nAé".to_string())
+        );
+        assert_eq!(
+            parse(r#"b"\x00\xff""#).unwrap(),
+            Literal::Bytes(vec![0, 255])
+        );
+        assert_eq!(
+            parse("DATE '2024-01-02'").unwrap(),
+            Literal::String("2024-01-02".to_string())
+        );
+        assert_eq!(parse("CAST(NULL AS INT64)").unwrap(), Literal::Null);
+        assert_eq!(
+            parse("ST_GEOGFROMTEXT('POINT(1 2)')").unwrap(),
+            Literal::String("POINT(1 2)".to_string())
+        );
+        assert_eq!(
+            parse("INTERVAL '1-2 3 4:5:6.789' YEAR TO SECOND").unwrap(),
+            Literal::String("1-2 3 4:5:6.789".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_composites() {
+        assert_eq!(
+            parse("STRUCT<`a` INT64, `b` ARRAY<STRING>>(1,[\"x\"])").unwrap(),
+            Literal::Struct(vec![
+                (None, Literal::Int64(1)),
+                (None, Literal::Array(vec![Literal::String("x".to_string())])),
+            ])
+        );
+        assert_eq!(
+            parse("STRUCT(1 AS `a`,\n  [] AS b)").unwrap(),
+            Literal::Struct(vec![
+                (Some("a".to_string()), Literal::Int64(1)),
+                (Some("b".to_string()), Literal::Array(vec![])),
+            ])
+        );
+        assert_eq!(
+            parse("ARRAY<STRUCT<a INT64>>[]").unwrap(),
+            Literal::Array(vec![])
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            parse("[1,2"),
+            Err(Error::Syntax { position: 4, .. })
+        ));
+        assert!(matches!(
+            parse("1 2"),
+            Err(Error::Syntax { position: 2, .. })
+        ));
+        assert!(matches!(
+            parse("nope"),
+            Err(Error::Syntax { position: 0, .. })
+        ));
+        assert!(matches!(parse("\"abc"), Err(Error::Syntax { .. })));
+    }
+
+    #[test]
+    fn test_parse_depth_limit() {
+        let nested = |depth| format!("{}1{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(matches!(
+            parse(&nested(MAX_DEPTH + 1)),
+            Err(Error::Syntax { position: 128, .. })
+        ));
+        assert!(matches!(
+            parse(&"[".repeat(200_000)),
+            Err(Error::Syntax { position: 128, .. })
+        ));
+        assert!(matches!(
+            parse(&"STRUCT(".repeat(200_000)),
+            Err(Error::Syntax { .. })
+        ));
+        assert!(matches!(
+            parse(&"CAST(".repeat(200_000)),
+            Err(Error::Syntax { .. })
+        ));
+    }
+}
@@ -1,6 +1,6 @@
 use std::fmt::{self, Display};
 
-use serde::ser;
+use serde::{de, ser};
 
 use crate::types::{Field, Type};
 
@@ -14,9 +14,33 @@ pub enum Error {
     UnsupportedType,
     EmptyStruct,
     InvalidIdentifierType(Type),
-    UnexpectedType { expected: Type, found: Type },
+    InvalidIdentifierValue {
+        found: Type,
+        value: String,
+    },
+    InvalidLiteralType(Type),
+    UnexpectedType {
+        expected: Type,
+        found: Type,
+    },
     UnexpectedStructField(Field),
     DuplicateStructField(String),
+    MixedFieldNaming,
+    InvalidOutput(String),
+    NestedArrayNotAllowed,
+    UnresolvedType(Type),
+    InvalidDecimal(String),
+    InvalidDate(String),
+    InvalidIntervalRange(String),
+    EmptyLiteral(Type),
+    Syntax {
+        position: usize,
+        expected: &'static str,
+    },
+    EmptyIdentifier,
+    StringTooLong {
+        len: usize,
+    },
 }
 
 impl Error {
@@ -35,6 +59,12 @@ impl ser::Error for Error {
     }
 }
 
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
 impl Display for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -48,6 +78,14 @@ impl Display for Error {
             Error::InvalidIdentifierType(t) => {
                 formatter.write_fmt(format_args!("invalid identifier type: {}", t))
             }
+            Error::InvalidIdentifierValue { found, value } => formatter.write_fmt(format_args!(
+                "invalid identifier: {} ({}), map keys must serialize to strings",
+                value, found
+            )),
+            Error::InvalidLiteralType(t) => formatter.write_fmt(format_args!(
+                "invalid literal type: {}, wrapped values must serialize to strings",
+                t
+            )),
             Error::UnexpectedType { expected, found } => formatter.write_fmt(format_args!(
                 "unexpected type: {} expected: {}",
                 found, expected
@@ -58,11 +96,42 @@ impl Display for Error {
             Error::DuplicateStructField(name) => {
                 formatter.write_fmt(format_args!("duplicate struct field: {}", name))
             }
+            Error::MixedFieldNaming => {
+                formatter.write_str("struct mixes named and positional fields")
+            }
+            Error::InvalidOutput(e) => {
+                formatter.write_fmt(format_args!("output isn't a valid expression: {}", e))
+            }
+            Error::NestedArrayNotAllowed => formatter.write_str("nested arrays are not allowed"),
+            Error::UnresolvedType(t) => formatter.write_fmt(format_args!("unresolved type: {}", t)),
+            Error::EmptyIdentifier => formatter.write_str("empty identifier"),
+            Error::StringTooLong { len } => {
+                formatter.write_fmt(format_args!("string too long: {} bytes", len))
+            }
+            Error::InvalidDecimal(s) => {
+                formatter.write_fmt(format_args!("invalid decimal: {:?}", s))
+            }
+            Error::InvalidDate(s) => formatter.write_fmt(format_args!("invalid date: {:?}", s)),
+            Error::InvalidIntervalRange(s) => {
+                formatter.write_fmt(format_args!("invalid interval range: {:?}", s))
+            }
+            Error::EmptyLiteral(t) => formatter.write_fmt(format_args!("empty {} literal", t)),
+            Error::Syntax { position, expected } => {
+                formatter.write_fmt(format_args!("expected {} at byte {}", expected, position))
+            }
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::IOError(err) => Some(err),
+            Error::FormattingError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
@@ -75,3 +144,27 @@ impl From<std::fmt::Error> for Error {
         Self::fmt(err)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::error::Error as _;
+    use std::io;
+
+    use super::*;
+    use crate::test_support::FailingWriter;
+
+    #[test]
+    fn test_io_error_source() {
+        let mut serializer = crate::Serializer::new(FailingWriter { remaining: 0 });
+        let err = serde::Serialize::serialize(&vec![1, 2], &mut serializer).unwrap_err();
+        assert!(matches!(err, Error::IOError(_)));
+        let source = err.source().unwrap();
+        assert_eq!(
+            source.downcast_ref::<io::Error>().unwrap().kind(),
+            io::ErrorKind::BrokenPipe
+        );
+
+        assert!(Error::fmt(fmt::Error).source().is_some());
+        assert!(Error::EmptyStruct.source().is_none());
+    }
+}
@@ -1,6 +1,33 @@
+mod codegen;
+mod de;
 mod error;
+#[cfg(feature = "serde_json")]
+mod schema;
 mod ser;
+#[cfg(test)]
+mod test_support;
 mod types;
+#[cfg(feature = "validate")]
+mod validate;
+mod warning;
+pub mod wrappers;
 
+pub use de::from_str;
 pub use error::{Error, Result};
-pub use ser::{to_bytes, to_string, Serializer};
+#[cfg(feature = "tokio")]
+pub use ser::to_async_writer;
+#[cfg(feature = "serde_json")]
+pub use ser::to_string_from_json;
+pub use ser::{
+    analyze_array, check_and_infer, estimated_query_bytes, to_bytes, to_bytes_typed,
+    to_bytes_with_config, to_bytes_with_scratch, to_bytes_with_type, to_insert_into,
+    to_multi_row_values, to_string, to_string_aliased, to_string_cast, to_string_f64_slice,
+    to_string_i64_slice, to_string_separated, to_string_typed, to_string_with,
+    to_string_with_config, to_string_with_type, to_unnest, to_values_row,
+    to_values_row_with_config, CaseStyle, CollisionPolicy, Config, FieldPredicate, JsonEmitMode,
+    NestedArrays, Scratch, Serializer, SerializerBuilder, TypeInferer, UnitRepr, ValueValidator,
+};
+pub use types::{Dialect, Field, Type, TypeDiff};
+#[cfg(feature = "validate")]
+pub use validate::validate_literal;
+pub use warning::Warning;
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+use crate::error::{Error, Result};
+use crate::types::{Dialect, Field, Type};
+
+impl Type {
+    /// Renders a struct type as a BigQuery JSON schema (the array of column definitions
+    /// accepted by `bq mk --schema`).
+    ///
+    /// `descriptions` are keyed by dotted field path (`"outer.inner"`) and end up in the
+    /// `description` of the matching column.
+    pub fn to_json_schema(&self, descriptions: &HashMap<String, String>) -> Result<Value> {
+        self.to_json_schema_with_dialect(descriptions, Dialect::Standard)
+    }
+
+    /// Like `to_json_schema` but with type names of the given dialect (`INTEGER`, `RECORD`
+    /// for `Dialect::Legacy`).
+    pub fn to_json_schema_with_dialect(
+        &self,
+        descriptions: &HashMap<String, String>,
+        dialect: Dialect,
+    ) -> Result<Value> {
+        match self {
+            Type::Struct(fields) => fields_schema(fields, "", descriptions, dialect),
+            _ => Err(Error::UnexpectedType {
+                expected: Type::Struct(vec![]),
+                found: self.clone(),
+            }),
+        }
+    }
+}
+
+fn fields_schema(
+    fields: &[Field],
+    prefix: &str,
+    descriptions: &HashMap<String, String>,
+    dialect: Dialect,
+) -> Result<Value> {
+    fields
+        .iter()
+        .map(|field| field_schema(field, prefix, descriptions, dialect))
+        .collect::<Result<Vec<Value>>>()
+        .map(Value::Array)
+}
+
+fn field_schema(
+    field: &Field,
+    prefix: &str,
+    descriptions: &HashMap<String, String>,
+    dialect: Dialect,
+) -> Result<Value> {
+    let name = match field.field_name {
+        Some(ref name) if !name.is_empty() => name,
+        _ => return Err(Error::Message("schema fields must be named".to_string())),
+    };
+    let path = format!("{}{}", prefix, name);
+
+    let (field_type, mode) = match field.field_type {
+        Type::Array(ref element_type) => (element_type.as_ref(), "REPEATED"),
+        ref field_type => (field_type, "NULLABLE"),
+    };
+
+    let mut schema = Map::new();
+    schema.insert("name".to_string(), Value::from(name.as_str()));
+    match field_type {
+        Type::Any => return Err(Error::UnresolvedType(field.field_type.clone())),
+        Type::Array(_) => return Err(Error::NestedArrayNotAllowed),
+        _ => {
+            schema.insert("type".to_string(), Value::from(field_type.name(dialect)));
+        }
+    }
+    match field_type {
+        Type::Numeric(Some((precision, scale))) => {
+            schema.insert("precision".to_string(), Value::from(precision.to_string()));
+            schema.insert("scale".to_string(), Value::from(scale.to_string()));
+        }
+        Type::String(Some(length)) | Type::Bytes(Some(length)) => {
+            schema.insert("maxLength".to_string(), Value::from(length.to_string()));
+        }
+        Type::Struct(fields) => {
+            schema.insert(
+                "fields".to_string(),
+                fields_schema(fields, &format!("{}.", path), descriptions, dialect)?,
+            );
+        }
+        _ => {}
+    }
+    schema.insert("mode".to_string(), Value::from(mode));
+    if let Some(description) = descriptions.get(&path) {
+        schema.insert("description".to_string(), Value::from(description.as_str()));
+    }
+    Ok(Value::Object(schema))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::field;
+    use serde_json::json;
+
+    #[test]
+    fn test_json_schema() {
+        let t = Type::Struct(vec![
+            field("id", Type::Int64),
+            field(
+                "tags",
+                Type::Array(Box::new(Type::Struct(vec![field(
+                    "name",
+                    Type::String(None),
+                )]))),
+            ),
+        ]);
+        let mut descriptions = HashMap::new();
+        descriptions.insert("tags.name".to_string(), "Tag name".to_string());
+
+        assert_eq!(
+            t.to_json_schema(&descriptions).unwrap(),
+            json!([
+                {"name": "id", "type": "INT64", "mode": "NULLABLE"},
+                {
+                    "name": "tags",
+                    "type": "STRUCT",
+                    "fields": [
+                        {
+                            "name": "name",
+                            "type": "STRING",
+                            "mode": "NULLABLE",
+                            "description": "Tag name"
+                        }
+                    ],
+                    "mode": "REPEATED"
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_json_schema_errors() {
+        let descriptions = HashMap::new();
+        assert!(Type::Int64.to_json_schema(&descriptions).is_err());
+        assert!(Type::Struct(vec![field("a", Type::Any)])
+            .to_json_schema(&descriptions)
+            .is_err());
+    }
+
+    #[test]
+    fn test_json_schema_legacy() {
+        let t = Type::Struct(vec![
+            field("ok", Type::Bool),
+            field(
+                "inner",
+                Type::Struct(vec![
+                    field("n", Type::Int64),
+                    field("x", Type::Array(Box::new(Type::Float64))),
+                ]),
+            ),
+        ]);
+        assert_eq!(
+            t.to_json_schema_with_dialect(&HashMap::new(), Dialect::Legacy)
+                .unwrap(),
+            json!([
+                {"name": "ok", "type": "BOOLEAN", "mode": "NULLABLE"},
+                {
+                    "name": "inner",
+                    "type": "RECORD",
+                    "fields": [
+                        {"name": "n", "type": "INTEGER", "mode": "NULLABLE"},
+                        {"name": "x", "type": "FLOAT", "mode": "REPEATED"}
+                    ],
+                    "mode": "NULLABLE"
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_json_schema_max_length() {
+        let t = Type::Struct(vec![
+            field("code", Type::String(Some(3))),
+            field("name", Type::String(None)),
+            field("hashes", Type::Array(Box::new(Type::Bytes(Some(32))))),
+        ]);
+        assert_eq!(
+            t.to_json_schema(&HashMap::new()).unwrap(),
+            json!([
+                {"name": "code", "type": "STRING", "maxLength": "3", "mode": "NULLABLE"},
+                {"name": "name", "type": "STRING", "mode": "NULLABLE"},
+                {"name": "hashes", "type": "BYTES", "maxLength": "32", "mode": "REPEATED"}
+            ])
+        );
+    }
+}
@@ -0,0 +1,254 @@
+use std::io;
+
+use serde::{ser, Serialize};
+
+use crate::error::{Error, Result};
+use crate::ser::serializer::Serializer;
+use crate::ser::unsupported::UnsupportedSerializer;
+use crate::types::Type;
+
+/// Infer the type of every element of an array separately
+///
+/// Unlike serializing, elements don't have to share a type, which helps finding the ones
+/// that don't fit.
+pub fn analyze_array<T>(value: &T) -> Result<Vec<(usize, Type)>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut types = Vec::new();
+    value.serialize(ArrayAnalyzer { types: &mut types })?;
+    Ok(types)
+}
+
+struct ArrayAnalyzer<'a> {
+    types: &'a mut Vec<(usize, Type)>,
+}
+
+impl<'a> ArrayAnalyzer<'a> {
+    fn not_an_array<O>(found: Type) -> Result<O> {
+        Err(Error::UnexpectedType {
+            expected: Type::any_array(),
+            found,
+        })
+    }
+}
+
+impl<'a> ser::Serializer for ArrayAnalyzer<'a> {
+    type Ok = Type;
+    type Error = Error;
+
+    type SerializeSeq = ElementTypes<'a>;
+    type SerializeTuple = UnsupportedSerializer;
+    type SerializeTupleStruct = UnsupportedSerializer;
+    type SerializeTupleVariant = UnsupportedSerializer;
+    type SerializeMap = UnsupportedSerializer;
+    type SerializeStruct = UnsupportedSerializer;
+    type SerializeStructVariant = UnsupportedSerializer;
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok> {
+        Self::not_an_array(Type::Bool)
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<Self::Ok> {
+        Self::not_an_array(Type::Int64)
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<Self::Ok> {
+        Self::not_an_array(Type::Int64)
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<Self::Ok> {
+        Self::not_an_array(Type::Int64)
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<Self::Ok> {
+        Self::not_an_array(Type::Int64)
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<Self::Ok> {
+        Self::not_an_array(Type::Int64)
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<Self::Ok> {
+        Self::not_an_array(Type::Int64)
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<Self::Ok> {
+        Self::not_an_array(Type::Int64)
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<Self::Ok> {
+        Self::not_an_array(Type::Int64)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok> {
+        Self::not_an_array(Type::Float64)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok> {
+        Self::not_an_array(Type::Float64)
+    }
+
+    fn serialize_char(self, _v: char) -> Result<Self::Ok> {
+        Self::not_an_array(Type::String(None))
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<Self::Ok> {
+        Self::not_an_array(Type::String(None))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok> {
+        Self::not_an_array(Type::Bytes(None))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok> {
+        Self::not_an_array(Type::Any)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok> {
+        Self::not_an_array(Type::Any)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        Self::not_an_array(Type::Any)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok> {
+        Self::not_an_array(Type::String(None))
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(Error::UnsupportedType)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(ElementTypes { types: self.types })
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Self::not_an_array(Type::Struct(vec![]))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Self::not_an_array(Type::Struct(vec![]))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(Error::UnsupportedType)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Self::not_an_array(Type::Struct(vec![]))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Self::not_an_array(Type::Struct(vec![]))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(Error::UnsupportedType)
+    }
+}
+
+struct ElementTypes<'a> {
+    types: &'a mut Vec<(usize, Type)>,
+}
+
+impl<'a> ser::SerializeSeq for ElementTypes<'a> {
+    type Ok = Type;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let element_type = value.serialize(&mut Serializer::new(io::sink()))?;
+        self.types.push((self.types.len(), element_type));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        Ok(Type::any_array())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::Field;
+
+    #[test]
+    fn test_analyze_mixed_array() {
+        #[derive(serde_derive::Serialize)]
+        #[serde(untagged)]
+        enum Mixed {
+            Int(u32),
+            Str(&'static str),
+            Bool(bool),
+        }
+
+        let v = vec![Mixed::Int(1), Mixed::Str("x"), Mixed::Bool(true)];
+        assert_eq!(
+            analyze_array(&v).unwrap(),
+            vec![(0, Type::Int64), (1, Type::String(None)), (2, Type::Bool)]
+        );
+        assert!(analyze_array(&1).is_err());
+    }
+
+    #[test]
+    fn test_analyze_structs() {
+        let v = vec![(1, "a"), (2, "b")];
+        let element_type = Type::Struct(vec![
+            Field::with_type_and_name(Type::Int64, None),
+            Field::with_type_and_name(Type::String(None), None),
+        ]);
+        assert_eq!(
+            analyze_array(&v).unwrap(),
+            vec![(0, element_type.clone()), (1, element_type)]
+        );
+    }
+}
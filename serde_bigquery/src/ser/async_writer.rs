@@ -0,0 +1,42 @@
+use serde::Serialize;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::error::{Error, Result};
+use crate::ser::serializer::to_bytes;
+
+/// Serialize value into an async writer
+///
+/// The whole value is serialized into a buffer first and only then written out, nothing is
+/// written if serialization fails.
+pub async fn to_async_writer<W, T>(writer: &mut W, value: &T) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: ?Sized + Serialize,
+{
+    let buffer = to_bytes(value)?;
+    writer.write_all(&buffer).await.map_err(Error::io)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_to_async_writer() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let mut output = Vec::new();
+        runtime
+            .block_on(to_async_writer(&mut output, &(1, "a")))
+            .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), r#"STRUCT(1,"a")"#);
+
+        let mut output = Vec::new();
+        let empty = std::collections::HashMap::<String, u32>::new();
+        assert!(runtime
+            .block_on(to_async_writer(&mut output, &empty))
+            .is_err());
+        assert!(output.is_empty());
+    }
+}
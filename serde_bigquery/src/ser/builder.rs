@@ -0,0 +1,183 @@
+use std::io;
+
+use crate::ser::{config::Config, identifier::CaseStyle, serializer::Serializer};
+
+/// Chainable alternative to filling in a `Config` for `Serializer::with_config`.
+///
+/// `SerializerBuilder::new().build(writer)` is the same as `Serializer::new(writer)`.
+#[derive(Clone, Debug, Default)]
+pub struct SerializerBuilder {
+    config: Config,
+}
+
+impl SerializerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts from an existing config.
+    pub fn with_config(config: Config) -> Self {
+        Self { config }
+    }
+
+    /// Indent by two spaces per nesting level, see `Serializer::pretty`.
+    pub fn pretty(self, pretty: bool) -> Self {
+        self.indent(if pretty { Some(2) } else { None })
+    }
+
+    /// See `Config::indent`.
+    pub fn indent(mut self, indent: Option<usize>) -> Self {
+        self.config.indent = indent;
+        self
+    }
+
+    /// Write `NULL` of a known type as `CAST(NULL AS T)`, see `Config::typed_null`.
+    pub fn null_casts(mut self, null_casts: bool) -> Self {
+        self.config.typed_null = null_casts;
+        self
+    }
+
+    /// See `Config::escape_unicode`.
+    pub fn escape_unicode(mut self, escape_unicode: bool) -> Self {
+        self.config.escape_unicode = escape_unicode;
+        self
+    }
+
+    /// See `Config::typed_structs`.
+    pub fn typed_structs(mut self, typed_structs: bool) -> Self {
+        self.config.typed_structs = typed_structs;
+        self
+    }
+
+    /// See `Config::rename_all`.
+    pub fn rename_all(mut self, case_style: Option<CaseStyle>) -> Self {
+        self.config.rename_all = case_style;
+        self
+    }
+
+    /// See `Config::sort_fields`.
+    pub fn sort_fields(mut self, sort_fields: bool) -> Self {
+        self.config.sort_fields = sort_fields;
+        self
+    }
+
+    /// Value of `is_human_readable()` seen by the serialized types, see `Config::compact`.
+    pub fn human_readable(mut self, human_readable: bool) -> Self {
+        self.config.compact = !human_readable;
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn build<W: io::Write>(self, writer: W) -> Serializer<W> {
+        Serializer::with_config(writer, self.config)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ser::config::FieldPredicate;
+    use crate::types::Type;
+    use serde::ser::Serialize;
+    use serde_derive::Serialize;
+
+    #[derive(Serialize)]
+    #[allow(non_snake_case)]
+    struct Row {
+        userName: &'static str,
+        scores: Vec<Option<u32>>,
+    }
+
+    fn serialize(builder: SerializerBuilder) -> String {
+        let row = Row {
+            userName: "ada",
+            scores: vec![Some(1), None],
+        };
+        let mut serializer = builder.build(Vec::new());
+        row.serialize(&mut serializer).unwrap();
+        String::from_utf8(serializer.writer).unwrap()
+    }
+
+    #[test]
+    fn test_builder() {
+        assert_eq!(
+            serialize(SerializerBuilder::new()),
+            r#"STRUCT("ada" AS `userName`,[1,NULL] AS `scores`)"#
+        );
+        assert_eq!(
+            serialize(
+                SerializerBuilder::new()
+                    .null_casts(true)
+                    .rename_all(Some(CaseStyle::SnakeCase))
+                    .pretty(true)
+            ),
+            "STRUCT(\n  \"ada\" AS `user_name`,\n  [\n    1,\n    CAST(NULL AS INT64)\n  ] AS `scores`\n)"
+        );
+    }
+
+    #[test]
+    fn test_human_readable() {
+        /// Hex string for humans, bytes otherwise (like `uuid::Uuid`).
+        struct Id([u8; 2]);
+
+        impl Serialize for Id {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                if serializer.is_human_readable() {
+                    serializer.serialize_str(&format!("{:02x}{:02x}", self.0[0], self.0[1]))
+                } else {
+                    serializer.serialize_bytes(&self.0)
+                }
+            }
+        }
+
+        let serialize = |builder: SerializerBuilder| {
+            let mut serializer = builder.build(Vec::new());
+            let t = vec![Id([0xab, 0x01]), Id([0, 0xff])]
+                .serialize(&mut serializer)
+                .unwrap();
+            (String::from_utf8(serializer.writer).unwrap(), t)
+        };
+        assert_eq!(
+            serialize(SerializerBuilder::new()),
+            (
+                r#"["ab01","00ff"]"#.to_string(),
+                Type::Array(Box::new(Type::String(None)))
+            )
+        );
+        assert_eq!(
+            serialize(SerializerBuilder::new().human_readable(false)),
+            (
+                r#"[b"\xab\x01",b"\x00\xff"]"#.to_string(),
+                Type::Array(Box::new(Type::Bytes(None)))
+            )
+        );
+    }
+
+    #[test]
+    fn test_shared_config() {
+        let config = Config {
+            redact: Some(FieldPredicate::new(|name| name == "userName")),
+            sort_fields: true,
+            ..Config::default()
+        };
+        assert!(format!("{:?}", config).contains("redact: Some(FieldPredicate)"));
+
+        let expected = r#"STRUCT([1,NULL] AS `scores`,"***" AS `userName`)"#;
+        let builder = SerializerBuilder::with_config(config.clone());
+        assert_eq!(serialize(builder.clone()), expected);
+        assert_eq!(serialize(builder), expected);
+
+        let handles: Vec<_> = (0..2)
+            .map(|_| {
+                let config = config.clone();
+                std::thread::spawn(move || serialize(SerializerBuilder::with_config(config)))
+            })
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), expected);
+        }
+    }
+}
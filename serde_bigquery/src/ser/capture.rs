@@ -0,0 +1,378 @@
+use serde::{ser, Serialize};
+
+use crate::error::{Error, Result};
+use crate::types;
+
+use super::{config::Config, unsupported::UnsupportedSerializer};
+
+/// Captures the string carried by a wrapper (e.g. `wrappers::Date`), anything else fails with
+/// `Error::InvalidLiteralType`.
+pub(crate) fn to_literal_string<T>(value: &T, config: &Config) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    let mut serializer = LiteralSerializer {
+        parts: Vec::new(),
+        tuple_len: None,
+        in_tuple: false,
+        human_readable: !config.compact,
+    };
+    value.serialize(&mut serializer)?;
+    Ok(serializer.parts.concat())
+}
+
+/// Like `to_literal_string` for wrappers carrying a tuple of `len` strings (e.g.
+/// `wrappers::Interval`).
+pub(crate) fn to_literal_strings<T>(value: &T, len: usize, config: &Config) -> Result<Vec<String>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut serializer = LiteralSerializer {
+        parts: Vec::new(),
+        tuple_len: Some(len),
+        in_tuple: false,
+        human_readable: !config.compact,
+    };
+    value.serialize(&mut serializer)?;
+    Ok(serializer.parts)
+}
+
+struct LiteralSerializer {
+    parts: Vec<String>,
+    /// Length of the tuple of strings expected instead of a single string.
+    tuple_len: Option<usize>,
+    in_tuple: bool,
+    /// See `Config::compact`.
+    human_readable: bool,
+}
+
+fn invalid_literal_type<T>(found: types::Type) -> Result<T> {
+    Err(Error::InvalidLiteralType(found))
+}
+
+impl ser::Serializer for &mut LiteralSerializer {
+    type Ok = types::Type;
+    type Error = Error;
+
+    type SerializeSeq = UnsupportedSerializer;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = UnsupportedSerializer;
+    type SerializeTupleVariant = UnsupportedSerializer;
+    type SerializeMap = UnsupportedSerializer;
+    type SerializeStruct = UnsupportedSerializer;
+    type SerializeStructVariant = UnsupportedSerializer;
+
+    fn is_human_readable(&self) -> bool {
+        self.human_readable
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok> {
+        invalid_literal_type(types::Type::Bool)
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<Self::Ok> {
+        invalid_literal_type(types::Type::Int64)
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<Self::Ok> {
+        invalid_literal_type(types::Type::Int64)
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<Self::Ok> {
+        invalid_literal_type(types::Type::Int64)
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<Self::Ok> {
+        invalid_literal_type(types::Type::Int64)
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<Self::Ok> {
+        invalid_literal_type(types::Type::Int64)
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<Self::Ok> {
+        invalid_literal_type(types::Type::Int64)
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<Self::Ok> {
+        invalid_literal_type(types::Type::Int64)
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<Self::Ok> {
+        invalid_literal_type(types::Type::Int64)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok> {
+        invalid_literal_type(types::Type::Float64)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok> {
+        invalid_literal_type(types::Type::Float64)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok> {
+        self.serialize_str(&v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok> {
+        if self.tuple_len.is_some() && !self.in_tuple {
+            return invalid_literal_type(types::Type::String(None));
+        }
+        self.parts.push(v.to_string());
+        Ok(types::Type::String(None))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok> {
+        invalid_literal_type(types::Type::Bytes(None))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok> {
+        invalid_literal_type(types::Type::Any)
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok> {
+        invalid_literal_type(types::Type::Any)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        invalid_literal_type(types::Type::Any)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok>
+    where
+        T: ?Sized + Serialize,
+    {
+        invalid_literal_type(types::Type::Struct(vec![]))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        invalid_literal_type(types::Type::any_array())
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        if self.tuple_len == Some(len) && !self.in_tuple {
+            self.in_tuple = true;
+            Ok(self)
+        } else {
+            invalid_literal_type(types::Type::Struct(vec![]))
+        }
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        invalid_literal_type(types::Type::Struct(vec![]))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        invalid_literal_type(types::Type::Struct(vec![]))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        invalid_literal_type(types::Type::Struct(vec![]))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        invalid_literal_type(types::Type::Struct(vec![]))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        invalid_literal_type(types::Type::Struct(vec![]))
+    }
+}
+
+impl ser::SerializeTuple for &mut LiteralSerializer {
+    type Ok = types::Type;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self).map(|_| ())
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        // the declared length doesn't have to match the number of elements
+        if Some(self.parts.len()) != self.tuple_len {
+            return invalid_literal_type(types::Type::Struct(vec![]));
+        }
+        Ok(types::Type::Struct(vec![]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_literal_string() {
+        let config = Config::default();
+        assert_eq!(
+            to_literal_string("2024-01-02", &config).unwrap(),
+            "2024-01-02"
+        );
+        assert_eq!(to_literal_string("", &config).unwrap(), "");
+        assert_eq!(to_literal_string(&Some('x'), &config).unwrap(), "x");
+        assert!(matches!(
+            to_literal_string(&12, &config),
+            Err(Error::InvalidLiteralType(types::Type::Int64))
+        ));
+        assert!(matches!(
+            to_literal_string(&vec!["a"], &config),
+            Err(Error::InvalidLiteralType(types::Type::Array(_)))
+        ));
+        assert_eq!(
+            to_literal_string(&None::<&str>, &config)
+                .unwrap_err()
+                .to_string(),
+            "invalid literal type: ?, wrapped values must serialize to strings"
+        );
+    }
+
+    #[test]
+    fn test_literal_strings() {
+        let config = Config::default();
+        assert_eq!(
+            to_literal_strings(&("DAY", "HOUR", "1 2"), 3, &config).unwrap(),
+            vec!["DAY", "HOUR", "1 2"]
+        );
+        assert!(matches!(
+            to_literal_strings("DAY HOUR 1 2", 3, &config),
+            Err(Error::InvalidLiteralType(types::Type::String(None)))
+        ));
+        assert!(to_literal_strings(&("DAY", "HOUR"), 3, &config).is_err());
+        assert!(to_literal_strings(&("DAY", ("HOUR", "1"), "2"), 3, &config).is_err());
+        assert!(to_literal_strings(&("DAY", 1, "2"), 3, &config).is_err());
+        assert!(to_literal_string(&("DAY",), &config).is_err());
+
+        struct Short;
+
+        impl Serialize for Short {
+            fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+            where
+                S: ser::Serializer,
+            {
+                use ser::SerializeTuple;
+
+                let mut tuple = serializer.serialize_tuple(3)?;
+                tuple.serialize_element("DAY")?;
+                tuple.serialize_element("HOUR")?;
+                tuple.end()
+            }
+        }
+
+        assert!(matches!(
+            to_literal_strings(&Short, 3, &config),
+            Err(Error::InvalidLiteralType(types::Type::Struct(_)))
+        ));
+
+        struct ShortInterval;
+
+        impl Serialize for ShortInterval {
+            fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+            where
+                S: ser::Serializer,
+            {
+                serializer.serialize_newtype_struct(crate::wrappers::INTERVAL_TOKEN, &Short)
+            }
+        }
+
+        assert!(matches!(
+            crate::to_string(&ShortInterval),
+            Err(Error::InvalidLiteralType(types::Type::Struct(_)))
+        ));
+    }
+
+    #[test]
+    fn test_human_readable() {
+        struct Readable;
+
+        impl Serialize for Readable {
+            fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+            where
+                S: ser::Serializer,
+            {
+                let readable = serializer.is_human_readable();
+                serializer.serialize_str(if readable { "readable" } else { "compact" })
+            }
+        }
+
+        let compact = Config {
+            compact: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            to_literal_string(&Readable, &Config::default()).unwrap(),
+            "readable"
+        );
+        assert_eq!(to_literal_string(&Readable, &compact).unwrap(), "compact");
+        assert_eq!(
+            to_literal_strings(&(&Readable, &Readable, &Readable), 3, &compact).unwrap(),
+            vec!["compact"; 3]
+        );
+        assert_eq!(
+            crate::ser::identifier::to_key_identifier(&Readable, &compact).unwrap(),
+            "compact"
+        );
+
+        struct Wrapped;
+
+        impl Serialize for Wrapped {
+            fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+            where
+                S: ser::Serializer,
+            {
+                serializer.serialize_newtype_struct(crate::wrappers::JSON_TOKEN, &Readable)
+            }
+        }
+
+        assert_eq!(
+            crate::to_string_with_config(&Wrapped, compact).unwrap(),
+            "JSON 'compact'"
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::ser::identifier::CaseStyle;
+use crate::types::Type;
+
+/// Options controlling how values are serialized.
+///
+/// `Config::default()` reproduces the behaviour of `Serializer::new`.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// Case style applied to every struct field name and map key before it is
+    /// formatted as an identifier.
+    pub rename_all: Option<CaseStyle>,
+    /// What to do with arrays directly nested in arrays.
+    pub nested_arrays: NestedArrays,
+    /// Write invalid values (e.g. a `wrappers::Date` that isn't a date) as `NULL` and record a
+    /// `Warning` instead of failing.
+    pub best_effort: bool,
+    /// Expected types of struct fields keyed by their dotted path (`"outer.inner"`, after
+    /// `rename_all`). Hinted fields are type checked and scalars are coerced into a hinted
+    /// `STRING`.
+    pub type_hints: HashMap<String, Type>,
+    /// Emit structs as `STRUCT<a INT64, b STRING>(1,"x")` instead of `STRUCT(1 AS a,"x" AS b)`.
+    ///
+    /// Fields get buffered until the whole struct is known. Fails with `Error::UnresolvedType`
+    /// if a field type can't be inferred (e.g. it's `NULL`).
+    pub typed_structs: bool,
+    /// Only infer the type of the value, nothing is written to the writer.
+    pub infer_only: bool,
+    /// Write NaN and infinities as `NULL` (typed as `FLOAT64`) instead of
+    /// `CAST('NaN' AS FLOAT64)`.
+    pub nonfinite_as_null: bool,
+    /// Write `DEFAULT` instead of `NULL` for absent and `None` fields of `VALUES` rows (see
+    /// `to_values_row_with_config`).
+    pub missing_as_default: bool,
+    /// Start a new line after an array element or struct field once the current line gets
+    /// this long. Continuation lines are indented by nesting depth.
+    pub line_width: Option<usize>,
+    /// Form of the literal written for `wrappers::Json`.
+    pub json_emit_mode: JsonEmitMode,
+    /// Write RFC 3339 timestamp strings (`"2024-01-02T03:04:05Z"`) as `TIMESTAMP '...'`
+    /// literals typed as `TIMESTAMP`.
+    ///
+    /// Strings in arrays (including fields of structs in arrays) stay `STRING`s, a single
+    /// element that isn't a timestamp would leave the array without a common element type.
+    pub detect_timestamps: bool,
+    /// Variants of enums (keyed by the enum name) to write as a `STRUCT` with a field per
+    /// variant, only the active one populated: `STRUCT(5 AS A,NULL AS B)`.
+    ///
+    /// Only newtype variants are supported.
+    pub enum_as_oneof_struct: HashMap<String, Vec<String>>,
+    /// Field names (after `rename_all`) in the order the outermost structs should be written
+    /// in, e.g. to match table columns. Absent fields are written as `NULL`, fields that
+    /// aren't listed are rejected.
+    ///
+    /// Applies to the top-level struct or the structs in a top-level array, not to the ones
+    /// nested in struct fields. Maps, tuples and enum variants keep their own fields.
+    pub field_order: Option<Vec<String>>,
+    /// Write every number as a string literal (`"42"`) typed as `STRING`.
+    pub numbers_as_strings: bool,
+    /// Fail with `Error::UnresolvedType` on `NULL` (`None`, `()`) unless its type is known
+    /// from a type hint, an expected type or earlier elements of the same array.
+    pub forbid_any: bool,
+    /// Fail with `Error::StringTooLong` on strings longer than this many bytes.
+    pub max_string_len: Option<usize>,
+    /// Write tuples whose element types all merge as an array (`(1, 2.5)` as `[1,2.5]`)
+    /// instead of a struct.
+    ///
+    /// Tuple elements get buffered until the whole tuple is known. Tuples of arrays, tuples
+    /// that are array elements, tuples expected to be structs and `VALUES` rows are still
+    /// written as structs.
+    pub homogeneous_tuple_as_array: bool,
+    /// Write `NULL` whose type is known from a type hint, an expected type or earlier elements
+    /// of the same array as `CAST(NULL AS INT64)`.
+    pub typed_null: bool,
+    /// How `()` and unit structs are written.
+    pub unit_repr: UnitRepr,
+    /// Pretty print: put every struct field and array element on its own line, indented by
+    /// this many spaces per nesting level. Takes precedence over `line_width`.
+    pub indent: Option<usize>,
+    /// Write structs using the tuple syntax `(1,"x")` (without field names) instead of
+    /// `STRUCT(1 AS a,"x" AS b)`. The returned type has unnamed fields to match.
+    ///
+    /// Fields get buffered until the whole struct is known as the syntax needs at least two
+    /// fields, single field structs are still written as `STRUCT(1 AS a)`. Ignored when
+    /// `typed_structs` is set.
+    pub bare_struct_parens: bool,
+    /// Struct fields (names after `rename_all`) to write as `"***"` instead of their value,
+    /// e.g. to log the output safely. The field itself is kept, typed as `STRING`.
+    pub redact: Option<FieldPredicate>,
+    /// Write struct fields ordered by name (after `rename_all`), e.g. to get reproducible
+    /// output for `HashMap`s. Fields get buffered until the whole struct is known.
+    ///
+    /// Structs with an expected type (e.g. later elements of an array) and `field_order`
+    /// follow the expected order instead.
+    pub sort_fields: bool,
+    /// What to do when two struct fields end up with the same name (after `rename_all`).
+    pub collision_policy: CollisionPolicy,
+    /// Write every non-ASCII character of strings as a `\uXXXX` (or `\UXXXXXXXX`) escape.
+    /// Control characters are escaped either way.
+    pub escape_unicode: bool,
+    /// Called with the type of every scalar before it's written, a rejection fails the
+    /// serialization with `Error::Message`. `NULL` is passed as `Type::Any`, even when written
+    /// as a typed `CAST`.
+    pub value_validator: Option<ValueValidator>,
+    /// Accept integer map keys, written as field names like `` `1` ``. Other non-string keys
+    /// are rejected either way.
+    pub allow_numeric_keys: bool,
+    /// Write finite floats the way BigQuery displays them (`1e+20`, `0.0001`, `1e-05`), e.g.
+    /// to compare with query results, instead of Rust's notation (`100000000000000000000`).
+    pub bigquery_float_format: bool,
+    /// Parse the output of `to_bytes_with_config` (and the functions built on it) as a SQL
+    /// expression and fail with `Error::InvalidOutput` if it isn't one, see
+    /// `validate_literal`.
+    ///
+    /// Needs the `validate` feature, without it serialization fails with `Error::Message`.
+    pub validate_output: bool,
+    /// Report `is_human_readable() == false` to the serialized types, so the ones with a
+    /// compact form use it (e.g. `uuid::Uuid` as bytes instead of a hyphenated string).
+    pub compact: bool,
+    /// Write empty strings as `NULL`. It's still typed as `STRING` so it merges with the other
+    /// strings of an array.
+    pub empty_string_as_null: bool,
+}
+
+/// Predicate on struct field names, see `Config::redact`.
+#[derive(Clone)]
+pub struct FieldPredicate(Arc<dyn Fn(&str) -> bool + Send + Sync>);
+
+impl FieldPredicate {
+    pub fn new<F>(predicate: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        Self(Arc::new(predicate))
+    }
+
+    pub fn matches(&self, field_name: &str) -> bool {
+        (self.0)(field_name)
+    }
+}
+
+impl fmt::Debug for FieldPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FieldPredicate")
+    }
+}
+
+/// Check of scalar types, see `Config::value_validator`.
+#[derive(Clone)]
+pub struct ValueValidator(Arc<ValidatorFn>);
+
+type ValidatorFn = dyn Fn(&Type) -> Result<(), String> + Send + Sync;
+
+impl ValueValidator {
+    pub fn new<F>(validator: F) -> Self
+    where
+        F: Fn(&Type) -> Result<(), String> + Send + Sync + 'static,
+    {
+        Self(Arc::new(validator))
+    }
+
+    pub fn validate(&self, value_type: &Type) -> Result<(), String> {
+        (self.0)(value_type)
+    }
+}
+
+impl fmt::Debug for ValueValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ValueValidator")
+    }
+}
+
+/// BigQuery doesn't allow `ARRAY<ARRAY<...>>`, an inner array has to be wrapped in a struct.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum NestedArrays {
+    /// Write them as they are (`[[1,2]]`), leaving it to BigQuery to reject the query.
+    #[default]
+    Allow,
+    /// Fail with `Error::NestedArrayNotAllowed` as soon as an inner array is opened.
+    Error,
+    /// Wrap every inner array in a single unnamed field struct: `[STRUCT([1,2])]`.
+    ///
+    /// Elements get buffered before they're written out.
+    WrapInStruct,
+}
+
+/// How a `wrappers::Json` value is written.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum JsonEmitMode {
+    /// `JSON '{"a":1}'`
+    #[default]
+    JsonLiteral,
+    /// `PARSE_JSON('{"a":1}')`, parsed more strictly than the literal.
+    ParseJson,
+    /// `CAST('{"a":1}' AS JSON)`
+    CastAsJson,
+}
+
+/// Handling of struct fields whose names collide, see `Config::collision_policy`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CollisionPolicy {
+    /// Fail with `Error::DuplicateStructField`.
+    #[default]
+    Error,
+    /// Append the lowest free counter starting at 2: `a_b`, `a_b_2`, `a_b_3`.
+    Suffix,
+}
+
+/// Representation of serde unit (`()`, unit structs).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum UnitRepr {
+    /// `NULL`
+    #[default]
+    Null,
+    /// Struct fields holding a unit are left out, elsewhere it's still `NULL`. A struct with
+    /// all fields left out fails with `Error::EmptyStruct`.
+    Omit,
+}
@@ -0,0 +1,95 @@
+/// Formats a finite float the way BigQuery displays `FLOAT64` values (`CAST(x AS STRING)`):
+/// like C's `%.15g`, but with the shortest digits that round-trip.
+///
+/// Scientific notation with a signed, at least two digit exponent (`1e+20`, `2.5e-05`) is used
+/// when the exponent is below -4 or at least the precision (15, or 17 for values needing more
+/// digits), plain decimal notation without trailing zeros otherwise.
+pub(crate) fn format_bigquery_float(v: f64) -> String {
+    // `{:e}` gives the shortest round-trip digits, e.g. `-1.2345e-5`
+    let scientific = format!("{:e}", v);
+    let (mantissa, exponent) = scientific
+        .split_once('e')
+        .expect("`{:e}` always has an exponent");
+    let exponent: i32 = exponent.parse().expect("`{:e}` exponent is an integer");
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let precision = if digits.len() <= 15 { 15 } else { 17 };
+
+    if exponent < -4 || exponent >= precision {
+        let exponent_sign = if exponent < 0 { '-' } else { '+' };
+        format!(
+            "{}{}e{}{:02}",
+            sign,
+            mantissa,
+            exponent_sign,
+            exponent.unsigned_abs()
+        )
+    } else if exponent < 0 {
+        let zeros = "0".repeat(exponent.unsigned_abs() as usize - 1);
+        format!("{}0.{}{}", sign, zeros, digits)
+    } else {
+        let integer_len = exponent as usize + 1;
+        if digits.len() <= integer_len {
+            format!("{}{:0<width$}", sign, digits, width = integer_len)
+        } else {
+            let (integer, fraction) = digits.split_at(integer_len);
+            format!("{}{}.{}", sign, integer, fraction)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_exponential_form() {
+        assert_eq!(format_bigquery_float(1e20), "1e+20");
+        assert_eq!(format_bigquery_float(1e15), "1e+15");
+        assert_eq!(format_bigquery_float(-1.5e300), "-1.5e+300");
+        assert_eq!(format_bigquery_float(0.00001), "1e-05");
+        assert_eq!(format_bigquery_float(2.5e-10), "2.5e-10");
+        assert_eq!(
+            format_bigquery_float(1.2345678901234567e17),
+            "1.2345678901234566e+17"
+        );
+    }
+
+    #[test]
+    fn test_decimal_form() {
+        assert_eq!(format_bigquery_float(0.0), "0");
+        assert_eq!(format_bigquery_float(-0.0), "-0");
+        assert_eq!(format_bigquery_float(1.0), "1");
+        assert_eq!(format_bigquery_float(1e14), "100000000000000");
+        assert_eq!(format_bigquery_float(123456.789), "123456.789");
+        assert_eq!(format_bigquery_float(-0.0001), "-0.0001");
+        assert_eq!(format_bigquery_float(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(
+            format_bigquery_float(1234567890123456.7),
+            "1234567890123456.8"
+        );
+        // 17 digit values switch to scientific notation later
+        assert_eq!(
+            format_bigquery_float(1.2345678901234567e16),
+            "12345678901234568"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for v in [
+            1e20,
+            2.5e-10,
+            0.1 + 0.2,
+            123456.789,
+            1e-300,
+            f64::MAX,
+            f64::MIN_POSITIVE,
+        ] {
+            assert_eq!(format_bigquery_float(v).parse::<f64>().unwrap(), v);
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use std::fmt::Write;
+use std::{
+    collections::HashSet,
+    fmt::{self, Write},
+};
 
 use serde::{ser, Serialize};
 
@@ -7,31 +10,158 @@ use crate::{
     types,
 };
 
-use super::unsupported::UnsupportedSerializer;
+use super::{config::Config, unsupported::UnsupportedSerializer};
+
+/// Case convention applied to field names before they're formatted as identifiers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CaseStyle {
+    /// `user_name`
+    SnakeCase,
+    /// `userName`
+    CamelCase,
+    /// `USER_NAME`
+    ScreamingSnakeCase,
+}
+
+impl CaseStyle {
+    pub fn apply(&self, name: &str) -> String {
+        let words = split_words(name);
+        match self {
+            CaseStyle::SnakeCase => words
+                .iter()
+                .map(|w| w.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+            CaseStyle::ScreamingSnakeCase => words
+                .iter()
+                .map(|w| w.to_uppercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+            CaseStyle::CamelCase => {
+                let mut result = String::new();
+                for (i, word) in words.iter().enumerate() {
+                    let word = word.to_lowercase();
+                    if i == 0 {
+                        result.push_str(&word);
+                    } else {
+                        let mut chars = word.chars();
+                        if let Some(first) = chars.next() {
+                            result.extend(first.to_uppercase());
+                            result.push_str(chars.as_str());
+                        }
+                    }
+                }
+                result
+            }
+        }
+    }
+}
+
+/// Splits a name into words on `_`, `-`, spaces and case transitions
+/// (`userName`, `HTTPServer`).
+fn split_words(name: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let chars: Vec<(usize, char)> = name.char_indices().collect();
+    let mut start = None;
+    for (i, &(pos, c)) in chars.iter().enumerate() {
+        if c == '_' || c == '-' || c == ' ' {
+            if let Some(s) = start.take() {
+                words.push(&name[s..pos]);
+            }
+            continue;
+        }
+        if let Some(s) = start {
+            let prev = chars[i - 1].1;
+            let next_is_lower = chars.get(i + 1).is_some_and(|&(_, n)| n.is_lowercase());
+            let boundary = c.is_uppercase()
+                && (prev.is_lowercase()
+                    || prev.is_ascii_digit()
+                    || (prev.is_uppercase() && next_is_lower));
+            if boundary {
+                words.push(&name[s..pos]);
+                start = Some(pos);
+            }
+        } else {
+            start = Some(pos);
+        }
+    }
+    if let Some(s) = start {
+        words.push(&name[s..]);
+    }
+    words
+}
 
 ///
 /// https://cloud.google.com/bigquery/docs/reference/standard-sql/lexical#identifiers
 pub fn format_as_identifier(s: &str) -> String {
-    // FIXME: handle ` in key
-    // FIXME: handle empty key
-    let mut result = String::new();
-    write!(result, "`{}`", s).unwrap();
+    // empty keys are rejected before they get here
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('`');
+    for c in s.chars() {
+        match c {
+            '`' => result.push_str("\\`"),
+            '\\' => result.push_str("\\\\"),
+            _ => result.push(c),
+        }
+    }
+    result.push('`');
     result
 }
 
-pub fn to_identifier<T>(value: &T) -> Result<String>
+/// `name` with the lowest `_N` suffix (N >= 2) that isn't `taken` yet, `taken` holds lowercased
+/// names.
+pub(crate) fn suffixed_identifier(name: &str, taken: &HashSet<String>) -> String {
+    (2..)
+        .map(|n| format!("{}_{}", name, n))
+        .find(|candidate| !taken.contains(&candidate.to_ascii_lowercase()))
+        .unwrap()
+}
+
+/// Whether the identifier can be written between backticks as is.
+pub(crate) fn needs_escaping(s: &str) -> bool {
+    s.contains(['`', '\\'])
+}
+
+/// Captures a map key as a field name. Keys have to serialize to strings, integers are
+/// accepted too with `Config::allow_numeric_keys`.
+pub(crate) fn to_key_identifier<T>(value: &T, config: &Config) -> Result<String>
 where
     T: ?Sized + Serialize,
 {
     let mut serializer = IdentifierSerializer {
         output: String::new(),
+        allow_numeric_keys: config.allow_numeric_keys,
+        human_readable: !config.compact,
     };
     value.serialize(&mut serializer)?;
     Ok(serializer.output)
 }
 
+/// Error for a scalar used where an identifier (e.g. a map key) is expected.
+fn invalid_identifier_value<T: ToString>(found: types::Type, value: T) -> Error {
+    Error::InvalidIdentifierValue {
+        found,
+        value: value.to_string(),
+    }
+}
+
 struct IdentifierSerializer {
     output: String,
+    allow_numeric_keys: bool,
+    /// See `Config::compact`.
+    human_readable: bool,
+}
+
+impl IdentifierSerializer {
+    fn serialize_integer<T: fmt::Display>(&mut self, v: T) -> Result<types::Type> {
+        if !self.allow_numeric_keys {
+            return Err(invalid_identifier_value(types::Type::Int64, v));
+        }
+        self.output
+            .write_fmt(format_args!("{}", v))
+            .map_err(Error::FormattingError)?;
+        Ok(types::Type::String(None))
+    }
 }
 
 impl ser::Serializer for &mut IdentifierSerializer {
@@ -46,48 +176,60 @@ impl ser::Serializer for &mut IdentifierSerializer {
     type SerializeStruct = UnsupportedSerializer;
     type SerializeStructVariant = UnsupportedSerializer;
 
-    fn serialize_bool(self, _v: bool) -> Result<Self::Ok> {
-        Err(Error::InvalidIdentifierType(types::Type::Bool))
+    fn is_human_readable(&self) -> bool {
+        self.human_readable
     }
 
-    fn serialize_i8(self, _v: i8) -> Result<Self::Ok> {
-        Err(Error::InvalidIdentifierType(types::Type::Number))
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
+        Err(invalid_identifier_value(types::Type::Bool, v))
     }
 
-    fn serialize_i16(self, _v: i16) -> Result<Self::Ok> {
-        Err(Error::InvalidIdentifierType(types::Type::Number))
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok> {
+        self.serialize_integer(v)
     }
 
-    fn serialize_i32(self, _v: i32) -> Result<Self::Ok> {
-        Err(Error::InvalidIdentifierType(types::Type::Number))
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok> {
+        self.serialize_integer(v)
     }
 
-    fn serialize_i64(self, _v: i64) -> Result<Self::Ok> {
-        Err(Error::InvalidIdentifierType(types::Type::Number))
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok> {
+        self.serialize_integer(v)
     }
 
-    fn serialize_u8(self, _v: u8) -> Result<Self::Ok> {
-        Err(Error::InvalidIdentifierType(types::Type::Number))
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
+        self.serialize_integer(v)
     }
 
-    fn serialize_u16(self, _v: u16) -> Result<Self::Ok> {
-        Err(Error::InvalidIdentifierType(types::Type::Number))
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
+        self.serialize_integer(v)
     }
 
-    fn serialize_u32(self, _v: u32) -> Result<Self::Ok> {
-        Err(Error::InvalidIdentifierType(types::Type::Number))
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok> {
+        self.serialize_integer(v)
     }
 
-    fn serialize_u64(self, _v: u64) -> Result<Self::Ok> {
-        Err(Error::InvalidIdentifierType(types::Type::Number))
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok> {
+        self.serialize_integer(v)
     }
 
-    fn serialize_f32(self, _v: f32) -> Result<Self::Ok> {
-        Err(Error::InvalidIdentifierType(types::Type::Number))
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
+        self.serialize_integer(v)
     }
 
-    fn serialize_f64(self, _v: f64) -> Result<Self::Ok> {
-        Err(Error::InvalidIdentifierType(types::Type::Number))
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok> {
+        self.serialize_integer(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok> {
+        self.serialize_integer(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
+        Err(invalid_identifier_value(types::Type::Float64, v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
+        Err(invalid_identifier_value(types::Type::Float64, v))
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok> {
@@ -96,30 +238,30 @@ impl ser::Serializer for &mut IdentifierSerializer {
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok> {
         self.output.write_str(v).map_err(Error::FormattingError)?;
-        Ok(types::Type::String)
+        Ok(types::Type::String(None))
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
         if let Ok(s) = std::str::from_utf8(v) {
             self.serialize_str(s)
         } else {
-            Err(Error::InvalidIdentifierType(types::Type::Bytes))
+            Err(Error::InvalidIdentifierType(types::Type::Bytes(None)))
         }
     }
 
     fn serialize_none(self) -> Result<Self::Ok> {
-        Ok(types::Type::String)
+        Ok(types::Type::String(None))
     }
 
-    fn serialize_some<T: ?Sized>(self, value: &T) -> Result<Self::Ok>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(self)
     }
 
     fn serialize_unit(self) -> Result<Self::Ok> {
-        Ok(types::Type::String)
+        Ok(types::Type::String(None))
     }
 
     fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok> {
@@ -135,14 +277,14 @@ impl ser::Serializer for &mut IdentifierSerializer {
         self.serialize_str(variant)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
@@ -150,7 +292,7 @@ impl ser::Serializer for &mut IdentifierSerializer {
         value: &T,
     ) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         value.serialize(self)
     }
@@ -201,3 +343,109 @@ impl ser::Serializer for &mut IdentifierSerializer {
         self.serialize_map(Some(len))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_support::count_allocations;
+
+    #[test]
+    fn test_invalid_map_key() {
+        let mut map = std::collections::BTreeMap::new();
+        map.insert(true, 1);
+        let err = crate::to_string(&map).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid identifier: true (BOOL), map keys must serialize to strings"
+        );
+    }
+
+    #[test]
+    fn test_numeric_keys() {
+        let mut map = std::collections::BTreeMap::new();
+        map.insert(2u32, "two".to_string());
+        map.insert(10u32, "ten".to_string());
+        let err = crate::to_string(&map).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid identifier: 2 (INT64), map keys must serialize to strings"
+        );
+
+        let config = Config {
+            allow_numeric_keys: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            crate::to_string_with_config(&map, config.clone()).unwrap(),
+            r#"STRUCT("two" AS `2`,"ten" AS `10`)"#
+        );
+        // floats still aren't names
+        assert!(to_key_identifier(&1.5, &config).is_err());
+    }
+
+    #[test]
+    fn test_char_keys() {
+        let mut map = std::collections::BTreeMap::new();
+        map.insert('a', 1);
+        map.insert('1', 2);
+        map.insert(' ', 3);
+        map.insert('`', 4);
+        let serialized = crate::to_string(&map).unwrap();
+        assert_eq!(
+            serialized,
+            r#"STRUCT(3 AS ` `,2 AS `1`,4 AS `\``,1 AS `a`)"#
+        );
+        assert_eq!(
+            crate::from_str::<std::collections::BTreeMap<char, i32>>(&serialized).unwrap(),
+            map
+        );
+        assert_eq!(format_as_identifier("a\\b"), r#"`a\\b`"#);
+    }
+
+    #[test]
+    fn test_needs_escaping() {
+        assert!(!needs_escaping("user_name"));
+        assert!(!needs_escaping("with space"));
+        assert!(needs_escaping("a`b"));
+        assert!(needs_escaping("a\\b"));
+    }
+
+    #[test]
+    fn test_wide_struct_identifiers() {
+        let map: std::collections::BTreeMap<String, usize> =
+            (0..100).map(|i| (format!("field_{:03}", i), i)).collect();
+        let expected = (0..100)
+            .map(|i| format!("{} AS `field_{:03}`", i, i))
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(
+            crate::to_string(&map).unwrap(),
+            format!("STRUCT({})", expected)
+        );
+
+        // names that need no escaping are written without formatting them into a `String`
+        let mut serializer = crate::Serializer::new(Vec::with_capacity(4096));
+        let (_, allocations) = count_allocations(|| {
+            for name in map.keys() {
+                serializer.write_identifier(name).unwrap();
+            }
+        });
+        assert_eq!(allocations, 0);
+        let (_, allocations) = count_allocations(|| serializer.write_identifier("a`b").unwrap());
+        assert!(allocations > 0);
+    }
+
+    #[test]
+    fn test_case_style() {
+        assert_eq!(CaseStyle::SnakeCase.apply("userName"), "user_name");
+        assert_eq!(CaseStyle::SnakeCase.apply("HTTPServer"), "http_server");
+        assert_eq!(CaseStyle::SnakeCase.apply("user_name"), "user_name");
+        assert_eq!(CaseStyle::CamelCase.apply("user_name"), "userName");
+        assert_eq!(CaseStyle::CamelCase.apply("UserName"), "userName");
+        assert_eq!(CaseStyle::ScreamingSnakeCase.apply("userName"), "USER_NAME");
+        assert_eq!(
+            CaseStyle::ScreamingSnakeCase.apply("user-name2"),
+            "USER_NAME2"
+        );
+    }
+}
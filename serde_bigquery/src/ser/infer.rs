@@ -0,0 +1,194 @@
+use std::io;
+
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::ser::config::Config;
+use crate::ser::serializer::Serializer;
+use crate::types::{Field, Type};
+
+/// Infers a type common to many values (e.g. rows of a table) without writing them.
+///
+/// Unlike an array, the values don't have to have the same struct fields: fields are matched
+/// by name and every field that's `NULL` or absent in some value gets `nullable` set.
+pub struct TypeInferer {
+    config: Config,
+    inferred: Option<Type>,
+}
+
+impl TypeInferer {
+    pub fn new() -> Self {
+        Self::with_config(Config::default())
+    }
+
+    pub fn with_config(config: Config) -> Self {
+        Self {
+            config: Config {
+                infer_only: true,
+                ..config
+            },
+            inferred: None,
+        }
+    }
+
+    /// Infers the type of `value` and unifies it with the values observed so far.
+    pub fn observe<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut serializer = Serializer::with_config(io::sink(), self.config.clone());
+        let value_type = mark_nullable(value.serialize(&mut serializer)?);
+        self.inferred = Some(match self.inferred {
+            Some(ref inferred) => {
+                unify(inferred, &value_type).ok_or_else(|| Error::UnexpectedType {
+                    expected: inferred.clone(),
+                    found: value_type,
+                })?
+            }
+            None => value_type,
+        });
+        Ok(())
+    }
+
+    /// The type of all observed values, `Type::Any` if there were none.
+    pub fn finish(self) -> Type {
+        self.inferred.unwrap_or(Type::Any)
+    }
+}
+
+impl Default for TypeInferer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sets `nullable` on struct fields that are `NULL` in a single value.
+fn mark_nullable(t: Type) -> Type {
+    match t {
+        Type::Struct(fields) => Type::Struct(
+            fields
+                .into_iter()
+                .map(|field| Field {
+                    nullable: field.nullable || field.field_type == Type::Any,
+                    field_type: mark_nullable(field.field_type),
+                    ..field
+                })
+                .collect(),
+        ),
+        Type::Array(element_type) => Type::Array(Box::new(mark_nullable(*element_type))),
+        t => t,
+    }
+}
+
+fn unify(t1: &Type, t2: &Type) -> Option<Type> {
+    match (t1, t2) {
+        (Type::Struct(fields), Type::Struct(other_fields)) => unify_structs(fields, other_fields),
+        (Type::Array(element_type), Type::Array(other_element_type)) => {
+            unify(element_type, other_element_type).map(|t| Type::Array(Box::new(t)))
+        }
+        (Type::Any, t) | (t, Type::Any) => Some(mark_nullable(t.clone())),
+        _ => t1.merge(t2),
+    }
+}
+
+/// Unifies struct fields by name, fields missing on either side become nullable.
+///
+/// Structs with unnamed fields are unified by position like `Type::merge` does.
+fn unify_structs(fields: &[Field], other_fields: &[Field]) -> Option<Type> {
+    let named = |fields: &[Field]| fields.iter().all(|f| f.field_name.is_some());
+    if !named(fields) || !named(other_fields) {
+        return Type::Struct(fields.to_vec()).merge(&Type::Struct(other_fields.to_vec()));
+    }
+
+    let find = |fields: &'_ [Field], name: &Option<String>| -> Option<Field> {
+        fields.iter().find(|f| &f.field_name == name).cloned()
+    };
+    let mut unified = Vec::with_capacity(fields.len());
+    for field in fields {
+        unified.push(match find(other_fields, &field.field_name) {
+            Some(other_field) => Field {
+                field_type: unify(&field.field_type, &other_field.field_type)?,
+                field_name: field.field_name.clone(),
+                nullable: field.nullable || other_field.nullable,
+            },
+            None => Field {
+                nullable: true,
+                ..field.clone()
+            },
+        });
+    }
+    for other_field in other_fields {
+        if find(fields, &other_field.field_name).is_none() {
+            unified.push(Field {
+                nullable: true,
+                ..other_field.clone()
+            });
+        }
+    }
+    Some(Type::Struct(unified))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_derive::Serialize;
+
+    #[test]
+    fn test_nullable_fields() {
+        #[derive(Serialize)]
+        struct Row {
+            id: u32,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            name: Option<&'static str>,
+            score: Option<f64>,
+        }
+
+        let rows = [
+            Row {
+                id: 1,
+                name: Some("a"),
+                score: None,
+            },
+            Row {
+                id: 2,
+                name: None,
+                score: Some(1.5),
+            },
+        ];
+        let mut inferer = TypeInferer::new();
+        for row in &rows {
+            inferer.observe(row).unwrap();
+        }
+        let field =
+            |name: &str, field_type| Field::with_type_and_name(field_type, Some(name.to_string()));
+        let inferred = inferer.finish();
+        assert_eq!(
+            inferred,
+            Type::Struct(vec![
+                field("id", Type::Int64),
+                field("name", Type::String(None)),
+                field("score", Type::Float64),
+            ])
+        );
+        let Type::Struct(fields) = inferred else {
+            unreachable!()
+        };
+        assert_eq!(
+            fields.iter().map(Field::is_nullable).collect::<Vec<_>>(),
+            vec![false, true, true]
+        );
+        // nullability doesn't take part in comparisons
+        assert_eq!(
+            field("id", Type::Int64).with_nullable(true),
+            field("id", Type::Int64)
+        );
+    }
+
+    #[test]
+    fn test_incompatible_values() {
+        let mut inferer = TypeInferer::new();
+        inferer.observe(&1).unwrap();
+        assert!(inferer.observe(&"a").is_err());
+        assert_eq!(inferer.finish(), Type::Int64);
+    }
+}
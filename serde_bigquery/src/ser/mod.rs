@@ -1,7 +1,35 @@
+mod analyze;
+#[cfg(feature = "tokio")]
+mod async_writer;
+mod builder;
+mod capture;
+mod config;
+mod float;
 pub(crate) mod identifier;
+mod infer;
+#[cfg(feature = "serde_json")]
+mod raw_value;
 pub(crate) mod serializer;
 pub(crate) mod struct_serializer;
+mod timestamp;
 pub(crate) mod typed_serializer;
 mod unsupported;
 
-pub use serializer::{to_bytes, to_string, Serializer};
+pub use analyze::analyze_array;
+#[cfg(feature = "tokio")]
+pub use async_writer::to_async_writer;
+pub use builder::SerializerBuilder;
+pub use config::{
+    CollisionPolicy, Config, FieldPredicate, JsonEmitMode, NestedArrays, UnitRepr, ValueValidator,
+};
+pub use identifier::CaseStyle;
+pub use infer::TypeInferer;
+#[cfg(feature = "serde_json")]
+pub use serializer::to_string_from_json;
+pub use serializer::{
+    check_and_infer, estimated_query_bytes, to_bytes, to_bytes_typed, to_bytes_with_config,
+    to_bytes_with_scratch, to_bytes_with_type, to_insert_into, to_multi_row_values, to_string,
+    to_string_aliased, to_string_cast, to_string_f64_slice, to_string_i64_slice,
+    to_string_separated, to_string_typed, to_string_with, to_string_with_config,
+    to_string_with_type, to_unnest, to_values_row, to_values_row_with_config, Scratch, Serializer,
+};
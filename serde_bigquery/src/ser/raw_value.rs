@@ -0,0 +1,24 @@
+use std::io;
+
+use serde::Serialize;
+
+use crate::error::{Error, Result};
+use crate::ser::serializer::Serializer;
+use crate::types::Type;
+
+/// Name of the struct (and its only field) `serde_json::value::RawValue` serializes as.
+pub(crate) const TOKEN: &str = "$serde_json::private::RawValue";
+
+/// Parses the JSON text carried by a `RawValue` and serializes it like any other value.
+pub(crate) fn transcode<W, T>(serializer: &mut Serializer<W>, value: &T) -> Result<Type>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    let json = match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(json)) => json,
+        _ => return Err(Error::Message("invalid raw JSON value".to_string())),
+    };
+    let mut deserializer = serde_json::Deserializer::from_str(&json);
+    serde_transcode::transcode(&mut deserializer, serializer)
+}
@@ -1,16 +1,113 @@
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fmt::Write as _;
 use std::io;
 use std::iter::FromIterator;
+use std::sync::Arc;
 
 use serde::{ser, Serialize};
 
 use crate::error::{Error, Result};
-use crate::ser::struct_serializer::StructSerializer;
+use crate::ser::capture::{to_literal_string, to_literal_strings};
+use crate::ser::config::{Config, JsonEmitMode, NestedArrays};
+use crate::ser::float::format_bigquery_float;
+use crate::ser::identifier::{format_as_identifier, needs_escaping};
+use crate::ser::infer::TypeInferer;
+use crate::ser::struct_serializer::{BufferedFields, StructSerializer};
+use crate::ser::timestamp::{is_date, is_timestamp};
 use crate::ser::typed_serializer::TypedSerializer;
-use crate::ser::unsupported::UnsupportedSerializer;
-use crate::types::Type;
+use crate::types::{Field, Type};
+use crate::warning::Warning;
+use crate::wrappers::{
+    double_quoted, is_decimal, single_quoted, BIGNUMERIC_TOKEN, DATETIME_TOKEN, DATE_TOKEN,
+    GEOGRAPHY_TOKEN, INTERVAL_PARTS, INTERVAL_TOKEN, JSON_TOKEN, NUMERIC_TOKEN, TIMESTAMP_TOKEN,
+};
 
+/// Name of the newtype struct `InactiveVariant` serializes as.
+pub(crate) const INACTIVE_VARIANT_TOKEN: &str = "$serde_bigquery::InactiveVariant";
+
+/// Value of the variants of a `Config::enum_as_oneof_struct` enum that aren't set.
+struct InactiveVariant;
+
+impl Serialize for InactiveVariant {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_newtype_struct(INACTIVE_VARIANT_TOKEN, &())
+    }
+}
+
+/// Writes values as BigQuery literals, returning their inferred types.
+///
+/// A failed serialization leaves the serializer mid-value: structs stay open, the output is
+/// partially written and the path still points at the failing field (see `current_path`).
+/// Don't reuse it for another value, create a new one instead.
 pub struct Serializer<W> {
     pub(crate) writer: W,
+    /// Shared with the buffer serializers of nested values.
+    pub(crate) config: Arc<Config>,
+    pub(crate) warnings: Vec<Warning>,
+    /// Names of the struct fields currently being serialized.
+    pub(crate) path: Vec<String>,
+    /// Columns of a `VALUES` row, taken by the first struct that gets serialized.
+    pub(crate) row_columns: Option<Vec<Field>>,
+    /// Number of arrays and structs currently open.
+    pub(crate) depth: usize,
+    /// Set while an array element is being serialized until a struct, tuple or array is
+    /// opened, so tuples directly in arrays aren't written as arrays (see
+    /// `Config::homogeneous_tuple_as_array`).
+    pub(crate) array_element: bool,
+    /// Set while the value of a struct field is serialized under `UnitRepr::Omit` until
+    /// anything but a unit is opened, a unit then writes nothing and sets `unit_omitted`.
+    pub(crate) omit_unit: bool,
+    pub(crate) unit_omitted: bool,
+    /// Number of arrays currently open, see `Config::detect_timestamps`.
+    pub(crate) open_arrays: usize,
+    /// Length of the current output line, tracked only when `Config::line_width` is set.
+    column: usize,
+    /// Cleared buffers ready to be reused by `buffer`, see `Scratch`.
+    spare_buffers: Vec<Vec<u8>>,
+    /// Cleared field lists ready to be reused by struct serializers, see `Scratch`.
+    spare_fields: Vec<Vec<Field>>,
+    /// Cleared maps ready to be reused for fields buffered out of order, see `Scratch`.
+    spare_buffered_fields: Vec<BufferedFields>,
+}
+
+/// Buffers kept by the caller between `to_bytes_with_scratch` calls so serializing many values
+/// doesn't allocate the output, the intermediate field buffers and the lists of struct fields
+/// again and again.
+///
+/// It also holds the config used for every value, so the config isn't cloned each time.
+#[derive(Debug, Default)]
+pub struct Scratch {
+    config: Arc<Config>,
+    output: Vec<u8>,
+    buffers: Vec<Vec<u8>>,
+    fields: Vec<Vec<Field>>,
+    buffered_fields: Vec<BufferedFields>,
+}
+
+impl Scratch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(config: Config) -> Self {
+        Self {
+            config: Arc::new(config),
+            ..Self::default()
+        }
+    }
+}
+
+/// Takes back spare values lent to a buffer serializer, without growing an emptied list.
+fn reclaim<T>(spare: &mut Vec<T>, lent: Vec<T>) {
+    if spare.is_empty() {
+        *spare = lent;
+    } else {
+        spare.extend(lent);
+    }
 }
 
 /// Serialize value to String
@@ -21,215 +118,1127 @@ where
     to_bytes(value).map(|v| String::from_utf8(v).unwrap())
 }
 
+/// Serialize a parsed JSON value to String
+///
+/// Numbers keep the form `serde_json` parsed them in: integers (`is_i64()`/`is_u64()`) are
+/// written as bare `INT64` literals (`1`, never `1.0`), anything else as `FLOAT64`.
+#[cfg(feature = "serde_json")]
+pub fn to_string_from_json(value: &serde_json::Value) -> Result<String> {
+    to_string(value)
+}
+
+/// Serialize values to String, one after another, separated by `separator`
+pub fn to_string_separated<'a, I, T>(values: I, separator: &str) -> Result<String>
+where
+    I: IntoIterator<Item = &'a T>,
+    T: 'a + ?Sized + Serialize,
+{
+    let mut serializer = Serializer::new(Vec::new());
+    for (i, value) in values.into_iter().enumerate() {
+        if i > 0 {
+            serializer.write_str(separator)?;
+        }
+        value.serialize(&mut serializer)?;
+    }
+    Ok(String::from_utf8(serializer.writer).unwrap())
+}
+
+/// Serialize value to String and pass it through `f`
+pub fn to_string_with<T, F>(value: &T, f: F) -> Result<String>
+where
+    T: ?Sized + Serialize,
+    F: FnOnce(String) -> String,
+{
+    to_string(value).map(f)
+}
+
+/// Serialize value to String followed by `AS alias`
+pub fn to_string_aliased<T>(value: &T, alias: &str) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    to_string(value).map(|s| format!("{} AS {}", s, format_as_identifier(alias)))
+}
+
 /// Serialize value to bytes
 pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>>
 where
     T: ?Sized + Serialize,
 {
-    let mut serializer = Serializer { writer: Vec::new() };
+    to_bytes_with_config(value, Config::default())
+}
+
+/// Serialize value to bytes reusing the buffers in `scratch`, using its config (see
+/// `Scratch::with_config`)
+///
+/// The returned bytes are valid until `scratch` is used again.
+pub fn to_bytes_with_scratch<'s, T>(value: &T, scratch: &'s mut Scratch) -> Result<&'s [u8]>
+where
+    T: ?Sized + Serialize,
+{
+    let mut output = std::mem::take(&mut scratch.output);
+    output.clear();
+    let mut serializer = Serializer::with_shared_config(output, Arc::clone(&scratch.config));
+    serializer.spare_buffers = std::mem::take(&mut scratch.buffers);
+    serializer.spare_fields = std::mem::take(&mut scratch.fields);
+    serializer.spare_buffered_fields = std::mem::take(&mut scratch.buffered_fields);
+    let result = value
+        .serialize(&mut serializer)
+        .map(|value_type| serializer.recycle_type(value_type));
+    scratch.output = serializer.writer;
+    scratch.buffers = serializer.spare_buffers;
+    scratch.fields = serializer.spare_fields;
+    scratch.buffered_fields = serializer.spare_buffered_fields;
+    result?;
+    validate_output(&scratch.config, &scratch.output)?;
+    Ok(&scratch.output)
+}
+
+/// Serialize value to String, returning its type too
+pub fn to_string_with_type<T>(value: &T) -> Result<(String, Type)>
+where
+    T: ?Sized + Serialize,
+{
+    to_bytes_with_type(value).map(|(v, t)| (String::from_utf8(v).unwrap(), t))
+}
+
+/// Serialize value to bytes, returning its type too
+pub fn to_bytes_with_type<T>(value: &T) -> Result<(Vec<u8>, Type)>
+where
+    T: ?Sized + Serialize,
+{
+    let mut serializer = Serializer::new(Vec::new());
+    let value_type = value.serialize(&mut serializer)?;
+    Ok((serializer.writer, value_type))
+}
+
+/// Serialize value to String, failing with `Error::UnexpectedType` unless it matches `expected`
+pub fn to_string_typed<T>(value: &T, expected: &Type) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    to_bytes_typed(value, expected).map(|v| String::from_utf8(v).unwrap())
+}
+
+/// Serialize value to bytes, failing with `Error::UnexpectedType` unless it matches `expected`
+pub fn to_bytes_typed<T>(value: &T, expected: &Type) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut serializer = Serializer::new(Vec::new());
+    value.serialize(&mut TypedSerializer::with_serializer(
+        &mut serializer,
+        expected,
+    ))?;
+    Ok(serializer.writer)
+}
+
+/// Serialize to String as `CAST(<literal> AS <type>)`, e.g. for a query parameter whose type
+/// BigQuery can't infer (`NULL`, an empty array).
+///
+/// The value is checked against `cast_type` like with `to_string_typed`, the type is rendered
+/// with `Type::to_ddl`.
+pub fn to_string_cast<T>(value: &T, cast_type: &Type) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    let ddl = cast_type.to_ddl()?;
+    let literal = to_string_typed(value, cast_type)?;
+    Ok(format!("CAST({} AS {})", literal, ddl))
+}
+
+/// Serialize value to String using the given config
+pub fn to_string_with_config<T>(value: &T, config: Config) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    to_bytes_with_config(value, config).map(|v| String::from_utf8(v).unwrap())
+}
+
+/// Serialize value to bytes using the given config
+pub fn to_bytes_with_config<T>(value: &T, config: Config) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut serializer = Serializer::with_config(Vec::new(), config);
     value.serialize(&mut serializer)?;
+    validate_output(&serializer.config, &serializer.writer)?;
     Ok(serializer.writer)
 }
 
-impl<W: io::Write> Serializer<W> {
-    pub fn new(writer: W) -> Self {
-        Self { writer }
+/// Checks the output when `Config::validate_output` is set.
+fn validate_output(config: &Config, output: &[u8]) -> Result<()> {
+    if !config.validate_output {
+        return Ok(());
     }
-
-    pub(crate) fn write(&mut self, buf: &[u8]) -> Result<()> {
-        self.writer.write_all(buf).map_err(Error::io)
+    #[cfg(feature = "validate")]
+    {
+        crate::validate::validate_literal(&String::from_utf8_lossy(output))
     }
+    #[cfg(not(feature = "validate"))]
+    {
+        let _ = output;
+        Err(Error::Message(
+            "`Config::validate_output` needs the `validate` feature".to_string(),
+        ))
+    }
+}
 
-    pub(crate) fn write_str(&mut self, s: &str) -> Result<()> {
-        self.write(s.as_bytes())
+/// Serialize an `i64` slice to String bypassing per element serde dispatch
+pub fn to_string_i64_slice(v: &[i64]) -> Result<String> {
+    let mut serializer = Serializer::new(Vec::new());
+    serializer.serialize_i64_slice(v)?;
+    Ok(String::from_utf8(serializer.writer).unwrap())
+}
+
+/// Serialize an `f64` slice to String bypassing per element serde dispatch
+pub fn to_string_f64_slice(v: &[f64]) -> Result<String> {
+    let mut serializer = Serializer::new(Vec::new());
+    serializer.serialize_f64_slice(v)?;
+    Ok(String::from_utf8(serializer.writer).unwrap())
+}
+
+/// Estimate how many bytes the literal adds to a query, which is its serialized length
+///
+/// Options making the output longer (e.g. `Config::typed_structs`) are accounted for.
+pub fn estimated_query_bytes<T>(value: &T, config: Config) -> Result<usize>
+where
+    T: ?Sized + Serialize,
+{
+    to_bytes_with_config(value, config).map(|bytes| bytes.len())
+}
+
+/// Serialize an array value to `UNNEST([...])`
+pub fn to_unnest<T>(value: &T) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    let mut serializer = Serializer::new(Vec::new());
+    let value_type = value.serialize(&mut serializer)?;
+    if let Type::Array(_) = value_type {
+        let output = String::from_utf8(serializer.writer).unwrap();
+        Ok(format!("UNNEST({})", output))
+    } else {
+        Err(Error::UnexpectedType {
+            expected: Type::any_array(),
+            found: value_type,
+        })
     }
+}
 
-    pub(crate) fn write_fmt(&mut self, fmt: std::fmt::Arguments<'_>) -> Result<()> {
-        self.writer.write_fmt(fmt).map_err(Error::io)
+/// Type check value against `expected` without writing it and return the inferred type
+///
+/// The returned type can be more specific than `expected`, e.g. resolve its `Any` fields.
+pub fn check_and_infer<T>(value: &T, expected: &Type) -> Result<Type>
+where
+    T: ?Sized + Serialize,
+{
+    let config = Config {
+        infer_only: true,
+        ..Config::default()
+    };
+    let mut serializer = Serializer::with_config(io::sink(), config);
+    value.serialize(&mut TypedSerializer::with_serializer(
+        &mut serializer,
+        expected,
+    ))
+}
+
+/// Serialize a struct or map to a `VALUES` row `(v1,v2,...)` with values in `columns` order
+pub fn to_values_row<T>(value: &T, columns: &[&str]) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    to_values_row_with_config(value, columns, Config::default())
+}
+
+/// Serialize a struct or map to a `VALUES` row `(v1,v2,...)` using the given config
+///
+/// Absent fields are filled with `NULL` (or `DEFAULT`, see `Config::missing_as_default`),
+/// fields not listed in `columns` are rejected.
+pub fn to_values_row_with_config<T>(value: &T, columns: &[&str], config: Config) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    let mut serializer = Serializer::with_config(Vec::new(), config);
+    serializer.serialize_row(
+        value,
+        columns
+            .iter()
+            .map(|column| Field::with_name(Some(column.to_string())))
+            .collect(),
+    )?;
+    Ok(String::from_utf8(serializer.writer).unwrap())
+}
+
+/// Serialize structs or maps to `VALUES` rows `(v1,v2,...),(v1,v2,...)` sharing one schema
+///
+/// The columns are inferred from all the rows (see `TypeInferer`), every row is written with
+/// its fields reordered to match them and the ones it lacks filled with `NULL`.
+pub fn to_multi_row_values<T>(rows: &[T]) -> Result<String>
+where
+    T: Serialize,
+{
+    multi_row_values(rows).map(|(_, values)| values)
+}
+
+/// Serialize structs or maps to `INSERT INTO table (c1,c2,...) VALUES (v1,v2,...),...`
+///
+/// Rows are written like by `to_multi_row_values` with the inferred columns listed explicitly
+/// so the table's column order doesn't matter. `table` may be qualified (`dataset.table`).
+pub fn to_insert_into<T>(table: &str, rows: &[T]) -> Result<String>
+where
+    T: Serialize,
+{
+    let (columns, values) = multi_row_values(rows)?;
+    let columns = columns
+        .iter()
+        .map(|column| format_as_identifier(column.field_name.as_deref().unwrap_or_default()))
+        .collect::<Vec<_>>()
+        .join(",");
+    Ok(format!(
+        "INSERT INTO {} ({}) VALUES {}",
+        format_as_identifier(table),
+        columns,
+        values
+    ))
+}
+
+fn multi_row_values<T>(rows: &[T]) -> Result<(Vec<Field>, String)>
+where
+    T: Serialize,
+{
+    let mut inferer = TypeInferer::new();
+    for row in rows {
+        inferer.observe(row)?;
     }
+    let columns = match inferer.finish() {
+        Type::Struct(fields) => fields,
+        found => {
+            return Err(Error::UnexpectedType {
+                expected: Type::Struct(vec![]),
+                found,
+            })
+        }
+    };
 
-    pub(crate) fn serialize<T>(&mut self, value: &T) -> Result<Type>
-    where
-        T: ?Sized + Serialize,
-    {
-        value.serialize(self)
+    let mut serializer = Serializer::new(Vec::new());
+    for (i, row) in rows.iter().enumerate() {
+        if i > 0 {
+            serializer.write(b",")?;
+        }
+        serializer.serialize_row(row, columns.clone())?;
     }
+    Ok((columns, String::from_utf8(serializer.writer).unwrap()))
 }
 
-impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
-    type Ok = Type;
-    type Error = Error;
+impl<W> Serializer<W> {
+    /// Applies the configured case style to a struct field name or map key.
+    pub(crate) fn field_name<'k>(&self, key: &'k str) -> Cow<'k, str> {
+        match self.config.rename_all {
+            Some(case_style) => Cow::Owned(case_style.apply(key)),
+            None => Cow::Borrowed(key),
+        }
+    }
 
-    type SerializeSeq = SeqSerializer<'a, W>;
-    type SerializeTuple = StructSerializer<'a, W>;
-    type SerializeTupleStruct = StructSerializer<'a, W>;
-    type SerializeTupleVariant = UnsupportedSerializer;
-    type SerializeMap = StructSerializer<'a, W>;
-    type SerializeStruct = StructSerializer<'a, W>;
-    type SerializeStructVariant = UnsupportedSerializer;
+    /// Warnings about values written differently than they'd be by default (see
+    /// `Config::best_effort`, `Config::type_hints` and `Config::detect_timestamps`) collected
+    /// so far.
+    pub fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
 
-    fn serialize_bool(self, v: bool) -> Result<Type> {
-        self.write(if v { b"TRUE" } else { b"FALSE" })
-            .map(|_| Type::Bool)
+    /// Creates a serializer with the same config that writes into a new buffer.
+    pub(crate) fn buffer(&mut self) -> Serializer<Vec<u8>> {
+        let writer = self.spare_buffers.pop().unwrap_or_default();
+        Serializer {
+            writer,
+            config: Arc::clone(&self.config),
+            warnings: Vec::new(),
+            path: self.path.clone(),
+            row_columns: None,
+            depth: self.depth,
+            array_element: false,
+            omit_unit: false,
+            unit_omitted: false,
+            open_arrays: self.open_arrays,
+            column: self.column,
+            // lent to the buffer for its own buffers until it's absorbed
+            spare_buffers: std::mem::take(&mut self.spare_buffers),
+            spare_fields: std::mem::take(&mut self.spare_fields),
+            spare_buffered_fields: std::mem::take(&mut self.spare_buffered_fields),
+        }
     }
 
-    fn serialize_i8(self, v: i8) -> Result<Type> {
-        self.serialize_i64(i64::from(v))
+    /// Takes over warnings (and spare buffers) collected by a buffer serializer and returns
+    /// its output.
+    pub(crate) fn absorb(&mut self, buffer: Serializer<Vec<u8>>) -> Vec<u8> {
+        self.warnings.extend(buffer.warnings);
+        reclaim(&mut self.spare_buffers, buffer.spare_buffers);
+        reclaim(&mut self.spare_fields, buffer.spare_fields);
+        reclaim(
+            &mut self.spare_buffered_fields,
+            buffer.spare_buffered_fields,
+        );
+        buffer.writer
     }
 
-    fn serialize_i16(self, v: i16) -> Result<Type> {
-        self.serialize_i64(i64::from(v))
+    /// Keeps an absorbed buffer that has been written out for reuse.
+    pub(crate) fn recycle(&mut self, mut buffer: Vec<u8>) {
+        buffer.clear();
+        self.spare_buffers.push(buffer);
     }
 
-    fn serialize_i32(self, v: i32) -> Result<Type> {
-        self.serialize_i64(i64::from(v))
+    /// An empty list for the fields of a struct, reused if possible.
+    pub(crate) fn field_list(&mut self) -> Vec<Field> {
+        self.spare_fields.pop().unwrap_or_default()
     }
 
-    fn serialize_i64(self, v: i64) -> Result<Type> {
-        self.write_str(&v.to_string()).map(|_| Type::Number)
+    /// Keeps the field lists of a type that's no longer needed for reuse.
+    pub(crate) fn recycle_type(&mut self, value_type: Type) {
+        match value_type {
+            Type::Struct(mut fields) => {
+                for field in fields.drain(..) {
+                    self.recycle_type(field.field_type);
+                }
+                self.spare_fields.push(fields);
+            }
+            Type::Array(element_type) => self.recycle_type(*element_type),
+            _ => {}
+        }
     }
 
-    fn serialize_u8(self, v: u8) -> Result<Type> {
-        self.serialize_u64(u64::from(v))
+    /// An empty map for fields buffered out of order, reused if possible.
+    pub(crate) fn buffered_fields(&mut self) -> BufferedFields {
+        self.spare_buffered_fields.pop().unwrap_or_default()
     }
 
-    fn serialize_u16(self, v: u16) -> Result<Type> {
-        self.serialize_u64(u64::from(v))
+    /// Keeps a drained map of buffered fields for reuse.
+    pub(crate) fn recycle_buffered_fields(&mut self, mut buffered_fields: BufferedFields) {
+        buffered_fields.clear();
+        self.spare_buffered_fields.push(buffered_fields);
     }
+}
 
-    fn serialize_u32(self, v: u32) -> Result<Type> {
-        self.serialize_u64(u64::from(v))
+impl<W: io::Write> Serializer<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_config(writer, Config::default())
     }
 
-    fn serialize_u64(self, v: u64) -> Result<Type> {
-        self.write_str(&v.to_string()).map(|_| Type::Number)
+    /// Serializer writing every struct field and array element on its own line, indented by
+    /// two spaces per nesting level (see `Config::indent`).
+    pub fn pretty(writer: W) -> Self {
+        Self::with_indent(writer, 2)
     }
 
-    fn serialize_f32(self, v: f32) -> Result<Type> {
-        self.serialize_f64(f64::from(v))
+    pub fn with_indent(writer: W, indent: usize) -> Self {
+        Self::with_config(
+            writer,
+            Config {
+                indent: Some(indent),
+                ..Config::default()
+            },
+        )
     }
 
-    fn serialize_f64(self, v: f64) -> Result<Type> {
-        self.write_str(&v.to_string()).map(|_| Type::Number)
+    pub fn with_config(writer: W, config: Config) -> Self {
+        Self::with_shared_config(writer, Arc::new(config))
     }
 
-    fn serialize_char(self, v: char) -> Result<Type> {
-        self.serialize_str(&v.to_string())
+    pub(crate) fn with_shared_config(writer: W, config: Arc<Config>) -> Self {
+        Self {
+            writer,
+            config,
+            warnings: Vec::new(),
+            path: Vec::new(),
+            row_columns: None,
+            depth: 0,
+            array_element: false,
+            omit_unit: false,
+            unit_omitted: false,
+            open_arrays: 0,
+            column: 0,
+            spare_buffers: Vec::new(),
+            spare_fields: Vec::new(),
+            spare_buffered_fields: Vec::new(),
+        }
     }
 
-    fn serialize_str(self, v: &str) -> Result<Type> {
-        // TODO: handle escape sequences (")
-        self.write_fmt(format_args!("\"{}\"", v))
-            .map(|_| Type::String)
+    pub(crate) fn write(&mut self, buf: &[u8]) -> Result<()> {
+        if self.config.infer_only {
+            return Ok(());
+        }
+        if self.config.line_width.is_some() {
+            match buf.iter().rposition(|&b| b == b'\n') {
+                Some(i) => self.column = buf.len() - i - 1,
+                None => self.column += buf.len(),
+            }
+        }
+        self.writer.write_all(buf).map_err(Error::io)
     }
 
-    fn serialize_bytes(self, v: &[u8]) -> Result<Type> {
-        // https://cloud.google.com/bigquery/docs/reference/standard-sql/lexical#string_and_bytes_literals
-        // TODO: (nice to have) use printable characters directly where possible
-        self.write(b"b\"")?;
-        self.write_str(&String::from_iter(
-            v.iter().map(|b| format!("\\x{:02x}", b)),
-        ))?;
-        self.write(b"\"").map(|_| Type::Bytes)
+    pub(crate) fn write_str(&mut self, s: &str) -> Result<()> {
+        self.write(s.as_bytes())
     }
 
-    fn serialize_none(self) -> Result<Type> {
-        self.write(b"NULL").map(|_| Type::Any)
+    pub(crate) fn write_fmt(&mut self, fmt: std::fmt::Arguments<'_>) -> Result<()> {
+        if self.config.infer_only {
+            return Ok(());
+        }
+        if self.config.line_width.is_some() {
+            return self.write_str(&fmt.to_string());
+        }
+        self.writer.write_fmt(fmt).map_err(Error::io)
     }
 
-    fn serialize_some<T>(self, value: &T) -> Result<Type>
-    where
-        T: ?Sized + Serialize,
-    {
-        value.serialize(self)
+    /// Writes a backticked identifier, without allocating unless it has to be escaped.
+    pub(crate) fn write_identifier(&mut self, s: &str) -> Result<()> {
+        if needs_escaping(s) {
+            return self.write_str(&format_as_identifier(s));
+        }
+        self.write(b"`")?;
+        self.write_str(s)?;
+        self.write(b"`")
     }
 
-    fn serialize_unit(self) -> Result<Type> {
-        self.serialize_none()
+    /// Leaves out a unit that's the whole value of a struct field (see `omit_unit`), returns
+    /// whether it did.
+    pub(crate) fn skip_unit(&mut self) -> bool {
+        if self.omit_unit {
+            self.omit_unit = false;
+            self.unit_omitted = true;
+            return true;
+        }
+        false
     }
 
-    fn serialize_unit_struct(self, _name: &'static str) -> Result<Type> {
-        self.serialize_unit()
+    /// Writes `NULL` regardless of `Config::forbid_any`, for when the type is known elsewhere.
+    pub(crate) fn write_null(&mut self) -> Result<Type> {
+        self.validate(&Type::Any)?;
+        self.write(b"NULL").map(|_| Type::Any)
     }
 
-    fn serialize_unit_variant(
-        self,
-        _name: &'static str,
-        _variant_index: u32,
-        variant: &'static str,
-    ) -> Result<Type> {
-        self.serialize_str(variant)
+    /// Checks the type of a scalar about to be written against `Config::value_validator`.
+    pub(crate) fn validate(&self, value_type: &Type) -> Result<()> {
+        match self.config.value_validator {
+            Some(ref validator) => validator.validate(value_type).map_err(Error::Message),
+            None => Ok(()),
+        }
+    }
+
+    /// Starts a new line indented by the current depth when `Config::indent` is set.
+    pub(crate) fn write_line_break(&mut self) -> Result<()> {
+        match self.config.indent {
+            Some(indent) => {
+                self.write(b"\n")?;
+                self.write_str(&" ".repeat(indent * self.depth))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the separator between array elements or struct fields.
+    pub(crate) fn write_separator(&mut self) -> Result<()> {
+        self.write(b",")?;
+        if self.config.indent.is_some() {
+            return self.write_line_break();
+        }
+        match self.config.line_width {
+            Some(line_width) if self.column >= line_width => {
+                self.write(b"\n")?;
+                self.write_str(&"  ".repeat(self.depth))
+            }
+            _ => Ok(()),
+        }
     }
 
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Type>
+    pub(crate) fn serialize<T>(&mut self, value: &T) -> Result<Type>
     where
         T: ?Sized + Serialize,
     {
         value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T>(
-        self,
-        _name: &'static str,
-        _variant_index: u32,
-        _variant: &'static str,
-        _value: &T,
-    ) -> Result<Type>
+    /// Fails with `Error::EmptyStruct` before anything is written if a struct or map is known
+    /// to have no fields (BigQuery has no zero-field structs), unless the fields come from
+    /// `VALUES` columns and get filled with `NULL`.
+    fn check_not_empty(&self, len: Option<usize>) -> Result<()> {
+        if len == Some(0) && self.row_columns.is_none() {
+            Err(Error::EmptyStruct)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Writes a struct or map as a `VALUES` row with fields in the order of `columns`.
+    fn serialize_row<T>(&mut self, value: &T, columns: Vec<Field>) -> Result<Type>
     where
         T: ?Sized + Serialize,
     {
-        Err(Error::UnsupportedType)
+        self.row_columns = Some(columns);
+        let row_type = self.serialize(value)?;
+        if self.row_columns.is_some() || !matches!(row_type, Type::Struct(_)) {
+            return Err(Error::UnexpectedType {
+                expected: Type::Struct(vec![]),
+                found: row_type,
+            });
+        }
+        Ok(row_type)
     }
 
-    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        self.write(b"[")
-            .map(move |_| SeqSerializer::with_serializer(self))
+    /// Serializes a struct field, checked against `expected_type` unless a type hint is
+    /// configured for its path.
+    pub(crate) fn serialize_field<T>(
+        &mut self,
+        key: Option<&str>,
+        value: &T,
+        expected_type: &Type,
+    ) -> Result<Type>
+    where
+        T: ?Sized + Serialize,
+    {
+        if let Some(key) = key {
+            self.path.push(key.to_string());
+        }
+        let result = match self.type_hint() {
+            Some(hint) => {
+                let mut typed_serializer =
+                    TypedSerializer::with_serializer(self, &hint).with_coercion();
+                value.serialize(&mut typed_serializer)
+            }
+            None if *expected_type == Type::Any => self.serialize(value),
+            None => value.serialize(&mut TypedSerializer::with_serializer(self, expected_type)),
+        };
+        // on error the path is kept so it points at the field that failed
+        if key.is_some() && result.is_ok() {
+            self.path.pop();
+        }
+        result
     }
 
-    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
-        if len > 0 {
-            self.write(b"STRUCT(")
-                .map(move |_| StructSerializer::with_serializer(self))
+    fn type_hint(&self) -> Option<Type> {
+        if self.config.type_hints.is_empty() {
+            None
         } else {
-            Err(Error::EmptyStruct)
+            self.config.type_hints.get(&self.current_path()).cloned()
         }
     }
 
-    fn serialize_tuple_struct(
-        self,
-        _name: &'static str,
-        len: usize,
-    ) -> Result<Self::SerializeTupleStruct> {
-        self.serialize_tuple(len)
+    /// Dotted path of the struct field currently being serialized, empty at the top level.
+    ///
+    /// After a failed serialization it's the path of the field that caused the error, it isn't
+    /// reset for the next value.
+    pub fn current_path(&self) -> String {
+        self.path.join(".")
     }
 
-    fn serialize_tuple_variant(
-        self,
-        _name: &'static str,
+    /// Writes an `i64` slice in one loop, producing the same output as serializing it as a seq.
+    ///
+    /// Unlike the seq path, elements aren't buffered and share one formatting buffer.
+    pub fn serialize_i64_slice(&mut self, v: &[i64]) -> Result<Type> {
+        if self.config.numbers_as_strings {
+            return self.serialize_slice(v, |serializer, x| {
+                ser::Serializer::serialize_i64(serializer, x)
+            });
+        }
+        let mut digits = String::new();
+        self.serialize_slice(v, |serializer, x| {
+            serializer.validate(&Type::Int64)?;
+            digits.clear();
+            write!(digits, "{}", x).map_err(Error::fmt)?;
+            serializer.write_str(&digits).map(|_| Type::Int64)
+        })
+    }
+
+    /// Writes an `f64` slice in one loop, producing the same output as serializing it as a seq.
+    ///
+    /// Unlike the seq path, elements aren't buffered and finite ones share one formatting
+    /// buffer.
+    pub fn serialize_f64_slice(&mut self, v: &[f64]) -> Result<Type> {
+        if self.config.numbers_as_strings || self.config.bigquery_float_format {
+            return self.serialize_slice(v, |serializer, x| {
+                ser::Serializer::serialize_f64(serializer, x)
+            });
+        }
+        let mut digits = String::new();
+        self.serialize_slice(v, |serializer, x| {
+            if !x.is_finite() {
+                return ser::Serializer::serialize_f64(&mut *serializer, x);
+            }
+            serializer.validate(&Type::Float64)?;
+            digits.clear();
+            write!(digits, "{}", x).map_err(Error::fmt)?;
+            serializer.write_str(&digits).map(|_| Type::Float64)
+        })
+    }
+
+    fn serialize_slice<E, F>(&mut self, v: &[E], mut f: F) -> Result<Type>
+    where
+        E: Copy,
+        F: FnMut(&mut Self, E) -> Result<Type>,
+    {
+        self.write(b"[")?;
+        self.depth += 1;
+        // all elements of a slice have the same type
+        let mut element_type = Type::Any;
+        for (i, x) in v.iter().enumerate() {
+            if i > 0 {
+                self.write_separator()?;
+            } else {
+                self.write_line_break()?;
+            }
+            element_type = f(self, *x)?;
+        }
+        self.depth -= 1;
+        if !v.is_empty() {
+            self.write_line_break()?;
+        }
+        self.write(b"]")?;
+        Ok(Type::Array(Box::new(element_type)))
+    }
+
+    /// Writes the JSON text carried by `wrappers::Json` in the configured form.
+    fn serialize_json<T>(&mut self, value: &T) -> Result<Type>
+    where
+        T: ?Sized + Serialize,
+    {
+        let json = single_quoted(&to_literal_string(value, &self.config)?);
+        self.validate(&Type::Json)?;
+        match self.config.json_emit_mode {
+            JsonEmitMode::JsonLiteral => self.write_fmt(format_args!("JSON {}", json)),
+            JsonEmitMode::ParseJson => self.write_fmt(format_args!("PARSE_JSON({})", json)),
+            JsonEmitMode::CastAsJson => self.write_fmt(format_args!("CAST({} AS JSON)", json)),
+        }
+        .map(|_| Type::Json)
+    }
+
+    /// Writes an enum variant as a struct with a field per variant, see
+    /// `Config::enum_as_oneof_struct`.
+    fn serialize_oneof<T>(&mut self, variants: &[String], variant: &str, value: &T) -> Result<Type>
+    where
+        T: ?Sized + Serialize,
+    {
+        if !variants.iter().any(|v| v == variant) {
+            return Err(Error::UnexpectedStructField(Field::with_name(Some(
+                variant.to_string(),
+            ))));
+        }
+        let mut struct_serializer = StructSerializer::open(self)?;
+        for v in variants {
+            if v == variant {
+                struct_serializer.serialize_field(Some(v), value)?;
+            } else {
+                struct_serializer.serialize_field(Some(v), &InactiveVariant)?;
+            }
+        }
+        ser::SerializeStruct::end(struct_serializer)
+    }
+
+    /// Writes the decimal carried by `wrappers::Numeric` or `wrappers::BigNumeric`.
+    fn serialize_decimal<T>(&mut self, value: &T, decimal_type: Type) -> Result<Type>
+    where
+        T: ?Sized + Serialize,
+    {
+        let decimal = to_literal_string(value, &self.config)?;
+        if !is_decimal(&decimal) {
+            return self.replace_with_null(decimal.clone(), Error::InvalidDecimal(decimal));
+        }
+        self.validate(&decimal_type)?;
+        self.write_fmt(format_args!("{} '{}'", decimal_type, decimal))
+            .map(|_| decimal_type)
+    }
+
+    /// Writes the date carried by `wrappers::Date`.
+    fn serialize_date<T>(&mut self, value: &T) -> Result<Type>
+    where
+        T: ?Sized + Serialize,
+    {
+        let date = to_literal_string(value, &self.config)?;
+        if !is_date(&date) {
+            return self.replace_with_null(date.clone(), Error::InvalidDate(date));
+        }
+        self.validate(&Type::Date)?;
+        self.write_fmt(format_args!("DATE '{}'", date))
+            .map(|_| Type::Date)
+    }
+
+    /// Writes the string carried by `wrappers::Timestamp` or `wrappers::Datetime`.
+    fn serialize_temporal<T>(&mut self, value: &T, temporal_type: Type) -> Result<Type>
+    where
+        T: ?Sized + Serialize,
+    {
+        let temporal = to_literal_string(value, &self.config)?;
+        if temporal.is_empty() {
+            return self.replace_with_null(temporal, Error::EmptyLiteral(temporal_type));
+        }
+        self.validate(&temporal_type)?;
+        self.write_fmt(format_args!(
+            "{} {}",
+            temporal_type,
+            single_quoted(&temporal)
+        ))
+        .map(|_| temporal_type)
+    }
+
+    /// Writes the WKT carried by `wrappers::Geography`.
+    fn serialize_geography<T>(&mut self, value: &T) -> Result<Type>
+    where
+        T: ?Sized + Serialize,
+    {
+        let wkt = to_literal_string(value, &self.config)?;
+        self.validate(&Type::Geography)?;
+        self.write_fmt(format_args!("ST_GEOGFROMTEXT({})", single_quoted(&wkt)))
+            .map(|_| Type::Geography)
+    }
+
+    /// Writes the range and body carried by `wrappers::Interval`.
+    fn serialize_interval<T>(&mut self, value: &T) -> Result<Type>
+    where
+        T: ?Sized + Serialize,
+    {
+        let parts = to_literal_strings(value, 3, &self.config)?;
+        let (from, to, body) = (parts[0].as_str(), parts[1].as_str(), parts[2].as_str());
+        let position = |part: &str| {
+            INTERVAL_PARTS
+                .iter()
+                .position(|p| p.eq_ignore_ascii_case(part))
+        };
+        match (position(from), position(to)) {
+            (Some(from), Some(to)) if from < to => {
+                self.validate(&Type::Interval)?;
+                self.write_fmt(format_args!(
+                    "INTERVAL {} {} TO {}",
+                    single_quoted(body),
+                    INTERVAL_PARTS[from],
+                    INTERVAL_PARTS[to]
+                ))
+                .map(|_| Type::Interval)
+            }
+            _ => {
+                let range = format!("{} TO {}", from, to);
+                let interval = format!("{} {}", single_quoted(body), range);
+                self.replace_with_null(interval, Error::InvalidIntervalRange(range))
+            }
+        }
+    }
+
+    /// Writes `NULL` in place of an invalid value when `Config::best_effort` is set, fails with
+    /// `err` otherwise.
+    fn replace_with_null(&mut self, value: String, err: Error) -> Result<Type> {
+        if self.config.best_effort {
+            self.write_null()?;
+            let path = self.current_path();
+            self.warnings
+                .push(Warning::ReplacedUnsupportedWithNull { path, value });
+            Ok(Type::Any)
+        } else {
+            Err(err)
+        }
+    }
+
+    /// Writes a 128-bit integer out of `INT64` range as `NUMERIC`, or `BIGNUMERIC` if it has
+    /// more than the 29 integer digits `NUMERIC` holds.
+    fn serialize_wide_integer(&mut self, v: &str) -> Result<Type> {
+        let digits = v.trim_start_matches('-').len();
+        let decimal_type = if digits <= 29 {
+            Type::Numeric(None)
+        } else {
+            Type::BigNumeric
+        };
+        self.validate(&decimal_type)?;
+        self.write_fmt(format_args!("{} '{}'", decimal_type, v))
+            .map(|_| decimal_type)
+    }
+}
+
+impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
+    type Ok = Type;
+    type Error = Error;
+
+    type SerializeSeq = SeqSerializer<'a, W>;
+    type SerializeTuple = StructSerializer<'a, W>;
+    type SerializeTupleStruct = StructSerializer<'a, W>;
+    type SerializeTupleVariant = StructSerializer<'a, W>;
+    type SerializeMap = StructSerializer<'a, W>;
+    type SerializeStruct = StructSerializer<'a, W>;
+    type SerializeStructVariant = StructSerializer<'a, W>;
+
+    fn is_human_readable(&self) -> bool {
+        !self.config.compact
+    }
+
+    fn serialize_bool(self, v: bool) -> Result<Type> {
+        self.validate(&Type::Bool)?;
+        self.write(if v { b"TRUE" } else { b"FALSE" })
+            .map(|_| Type::Bool)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Type> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Type> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Type> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Type> {
+        if self.config.numbers_as_strings {
+            return self.serialize_str(&v.to_string());
+        }
+        self.validate(&Type::Int64)?;
+        self.write_str(&v.to_string()).map(|_| Type::Int64)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Type> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Type> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Type> {
+        self.serialize_u64(u64::from(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Type> {
+        match i64::try_from(v) {
+            Ok(v) => self.serialize_i64(v),
+            Err(_) => self.serialize_wide_integer(&v.to_string()),
+        }
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Type> {
+        match u64::try_from(v) {
+            Ok(v) => self.serialize_u64(v),
+            Err(_) => self.serialize_wide_integer(&v.to_string()),
+        }
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Type> {
+        if self.config.numbers_as_strings {
+            return self.serialize_str(&v.to_string());
+        }
+        self.validate(&Type::Int64)?;
+        self.write_str(&v.to_string()).map(|_| Type::Int64)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Type> {
+        self.serialize_f64(f64::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Type> {
+        if self.config.numbers_as_strings {
+            return self.serialize_str(&v.to_string());
+        }
+        if !v.is_finite() && self.config.nonfinite_as_null {
+            // still FLOAT64 so it merges with the finite values in an array
+            return self.write_null().map(|_| Type::Float64);
+        }
+        self.validate(&Type::Float64)?;
+        if v.is_finite() && self.config.bigquery_float_format {
+            self.write_str(&format_bigquery_float(v))
+        } else if v.is_finite() {
+            self.write_str(&v.to_string())
+        } else if v.is_nan() {
+            self.write(b"CAST('NaN' AS FLOAT64)")
+        } else if v.is_sign_positive() {
+            self.write(b"CAST('inf' AS FLOAT64)")
+        } else {
+            self.write(b"CAST('-inf' AS FLOAT64)")
+        }
+        .map(|_| Type::Float64)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Type> {
+        self.serialize_str(&v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Type> {
+        if let Some(max_string_len) = self.config.max_string_len {
+            if v.len() > max_string_len {
+                return Err(Error::StringTooLong { len: v.len() });
+            }
+        }
+        if self.config.empty_string_as_null && v.is_empty() {
+            return self.write_null().map(|_| Type::String(None));
+        }
+        if self.config.detect_timestamps && self.open_arrays == 0 && is_timestamp(v) {
+            self.validate(&Type::Timestamp)?;
+            let path = self.current_path();
+            self.warnings
+                .push(Warning::CoercedStringToTimestamp { path });
+            return self
+                .write_fmt(format_args!("TIMESTAMP {}", single_quoted(v)))
+                .map(|_| Type::Timestamp);
+        }
+        self.validate(&Type::String(None))?;
+        self.write_str(&double_quoted(v, self.config.escape_unicode))
+            .map(|_| Type::String(None))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Type> {
+        // https://cloud.google.com/bigquery/docs/reference/standard-sql/lexical#string_and_bytes_literals
+        // TODO: (nice to have) use printable characters directly where possible
+        // Only reached for `serde_bytes` wrapped values (`Vec<u8>`, `&[u8]`, `Cow<[u8]>`), there's
+        // no option turning plain byte sequences into bytes - those end up as `ARRAY<INT64>`.
+        self.validate(&Type::Bytes(None))?;
+        self.write(b"b\"")?;
+        self.write_str(&String::from_iter(
+            v.iter().map(|b| format!("\\x{:02x}", b)),
+        ))?;
+        self.write(b"\"").map(|_| Type::Bytes(None))
+    }
+
+    fn serialize_none(self) -> Result<Type> {
+        if self.config.forbid_any {
+            return Err(Error::UnresolvedType(Type::Any));
+        }
+        self.write_null()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Type>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.omit_unit = false;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Type> {
+        if self.skip_unit() {
+            return Ok(Type::Any);
+        }
+        self.serialize_none()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Type> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Type> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Type>
+    where
+        T: ?Sized + Serialize,
+    {
+        match name {
+            JSON_TOKEN => self.serialize_json(value),
+            NUMERIC_TOKEN => self.serialize_decimal(value, Type::Numeric(None)),
+            BIGNUMERIC_TOKEN => self.serialize_decimal(value, Type::BigNumeric),
+            DATE_TOKEN => self.serialize_date(value),
+            TIMESTAMP_TOKEN => self.serialize_temporal(value, Type::Timestamp),
+            DATETIME_TOKEN => self.serialize_temporal(value, Type::Datetime),
+            GEOGRAPHY_TOKEN => self.serialize_geography(value),
+            INTERVAL_TOKEN => self.serialize_interval(value),
+            // the variant's type is only known from the other rows
+            INACTIVE_VARIANT_TOKEN => self.write_null(),
+            _ => value.serialize(self),
+        }
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Type>
+    where
+        T: ?Sized + Serialize,
+    {
+        if let Some(variants) = self.config.enum_as_oneof_struct.get(name).cloned() {
+            return self.serialize_oneof(&variants, variant, value);
+        }
+        // externally tagged like in serde_json: `STRUCT(value AS variant)`
+        let mut struct_serializer = StructSerializer::open(self)?;
+        struct_serializer.serialize_field(Some(variant), value)?;
+        ser::SerializeStruct::end(struct_serializer)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        // fail before any of the inner array gets written
+        if self.array_element && self.config.nested_arrays == NestedArrays::Error {
+            return Err(Error::NestedArrayNotAllowed);
+        }
+        // `[` is written by `SeqSerializer` as an empty array might need a type in front
+        self.depth += 1;
+        self.open_arrays += 1;
+        self.array_element = false;
+        self.omit_unit = false;
+        Ok(SeqSerializer::with_serializer(self))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        if len > 0 {
+            StructSerializer::open_tuple(self)
+        } else {
+            Err(Error::EmptyStruct)
+        }
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        if len > 0 {
+            StructSerializer::open(self)
+        } else {
+            Err(Error::EmptyStruct)
+        }
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        Err(Error::UnsupportedType)
+        self.check_not_empty(Some(len))?;
+        StructSerializer::open_variant(self, variant)
     }
 
-    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        self.write(b"STRUCT(")
-            .map(move |_| StructSerializer::with_serializer(self))
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+        self.check_not_empty(len)?;
+        StructSerializer::open(self)
     }
 
-    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
-        self.serialize_tuple(len)
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        #[cfg(feature = "serde_json")]
+        if name == crate::ser::raw_value::TOKEN {
+            return Ok(StructSerializer::raw_value(self));
+        }
+        #[cfg(not(feature = "serde_json"))]
+        let _ = name;
+        // fields missing from `Config::field_order` get filled with `NULL`
+        if self.config.field_order.is_none() || !self.path.is_empty() {
+            self.check_not_empty(Some(len))?;
+        }
+        StructSerializer::open_struct(self)
     }
 
     fn serialize_struct_variant(
         self,
         _name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
-        _len: usize,
+        variant: &'static str,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        Err(Error::UnsupportedType)
+        self.check_not_empty(Some(len))?;
+        StructSerializer::open_variant(self, variant)
     }
 }
 
@@ -256,6 +1265,40 @@ impl<'a, W> SeqSerializer<'a, W> {
     }
 }
 
+impl<'a, W: io::Write> SeqSerializer<'a, W> {
+    /// Serializes the element into a buffer first so inner arrays can be wrapped in `STRUCT(...)`.
+    fn serialize_wrapped_element<T>(&mut self, value: &T) -> Result<Type>
+    where
+        T: ?Sized + Serialize,
+    {
+        let expected_type = match self.element_type {
+            Type::Struct(ref fields) if fields.len() == 1 && fields[0].field_name.is_none() => {
+                match fields[0].field_type {
+                    Type::Array(_) => &fields[0].field_type,
+                    _ => &self.element_type,
+                }
+            }
+            _ => &self.element_type,
+        };
+        let mut buffer = self.serializer.buffer();
+        buffer.array_element = true;
+        let mut typed_serializer = TypedSerializer::with_serializer(&mut buffer, expected_type);
+        let element_type = value.serialize(&mut typed_serializer)?;
+        let buffer = self.serializer.absorb(buffer);
+        let element_type = if let Type::Array(_) = element_type {
+            self.serializer.write(b"STRUCT(")?;
+            self.serializer.write(&buffer)?;
+            self.serializer.write(b")")?;
+            Type::Struct(vec![Field::with_type_and_name(element_type, None)])
+        } else {
+            self.serializer.write(&buffer)?;
+            element_type
+        };
+        self.serializer.recycle(buffer);
+        Ok(element_type)
+    }
+}
+
 impl<'a, W: io::Write> ser::SerializeSeq for SeqSerializer<'a, W> {
     type Ok = Type;
     type Error = Error;
@@ -265,16 +1308,40 @@ impl<'a, W: io::Write> ser::SerializeSeq for SeqSerializer<'a, W> {
         T: ?Sized + Serialize,
     {
         if self.has_elements {
-            self.serializer.write(b",")?;
+            self.serializer.write_separator()?;
         } else {
+            self.serializer.write(b"[")?;
+            self.serializer.write_line_break()?;
             self.has_elements = true;
         }
-        let mut typed_serializer =
-            TypedSerializer::with_serializer(self.serializer, &self.element_type);
-        let element_type = value.serialize(&mut typed_serializer)?;
+        let element_type = match self.serializer.config.nested_arrays {
+            nested_arrays @ (NestedArrays::Allow | NestedArrays::Error) => {
+                self.serializer.array_element = true;
+                let mut typed_serializer =
+                    TypedSerializer::with_serializer(self.serializer, &self.element_type);
+                let element_type = value.serialize(&mut typed_serializer)?;
+                // e.g. a `NULL` of an expected array type
+                if nested_arrays == NestedArrays::Error && matches!(element_type, Type::Array(_)) {
+                    return Err(Error::NestedArrayNotAllowed);
+                }
+                element_type
+            }
+            NestedArrays::WrapInStruct => self.serialize_wrapped_element(value)?,
+        };
+        // the first element's type is taken as it is, most of the others match it
+        if self.element_type == Type::Any {
+            self.element_type = element_type;
+            return Ok(());
+        }
+        if element_type == self.element_type {
+            self.serializer.recycle_type(element_type);
+            return Ok(());
+        }
         let new_element_type = self.element_type.merge(&element_type);
         if let Some(merged_element_type) = new_element_type {
-            self.element_type = merged_element_type;
+            let previous = std::mem::replace(&mut self.element_type, merged_element_type);
+            self.serializer.recycle_type(previous);
+            self.serializer.recycle_type(element_type);
             Ok(())
         } else {
             Err(Error::UnexpectedType {
@@ -285,9 +1352,23 @@ impl<'a, W: io::Write> ser::SerializeSeq for SeqSerializer<'a, W> {
     }
 
     fn end(self) -> Result<Type> {
-        self.serializer
-            .write(b"]")
-            .map(|_| Type::Array(Box::new(self.element_type)))
+        self.serializer.depth -= 1;
+        self.serializer.open_arrays -= 1;
+        self.serializer.array_element = false;
+        // without elements the struct type is only known if it was expected
+        let write_type = !self.has_elements
+            && matches!(self.element_type, Type::Struct(_))
+            && !self.element_type.contains_any();
+        let array_type = Type::Array(Box::new(self.element_type));
+        if write_type {
+            self.serializer.write_fmt(format_args!("{}", array_type))?;
+        }
+        if self.has_elements {
+            self.serializer.write_line_break()?;
+        } else {
+            self.serializer.write(b"[")?;
+        }
+        self.serializer.write(b"]").map(|_| array_type)
     }
 }
 
@@ -296,6 +1377,9 @@ impl<'a, W: io::Write> ser::SerializeSeq for SeqSerializer<'a, W> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::ser::config::{CollisionPolicy, FieldPredicate, UnitRepr, ValueValidator};
+    use crate::ser::identifier::CaseStyle;
+    use crate::test_support::{count_allocations, FailingWriter};
     use serde::ser::{SerializeSeq, SerializeStruct, Serializer};
     use serde_bytes::Bytes;
     use serde_derive::Serialize;
@@ -308,68 +1392,2082 @@ mod test {
         assert_eq!(to_string(&1.25).unwrap(), "1.25");
     }
 
+    #[test]
+    fn test_nonfinite_floats() {
+        assert_eq!(to_string(&f64::NAN).unwrap(), "CAST('NaN' AS FLOAT64)");
+        assert_eq!(to_string(&f64::INFINITY).unwrap(), "CAST('inf' AS FLOAT64)");
+        assert_eq!(
+            to_string(&f64::NEG_INFINITY).unwrap(),
+            "CAST('-inf' AS FLOAT64)"
+        );
+        assert_eq!(to_string(&f32::NAN).unwrap(), "CAST('NaN' AS FLOAT64)");
+        assert_eq!(to_string(&f32::INFINITY).unwrap(), "CAST('inf' AS FLOAT64)");
+        assert_eq!(
+            to_string(&f32::NEG_INFINITY).unwrap(),
+            "CAST('-inf' AS FLOAT64)"
+        );
+
+        let config = Config {
+            nonfinite_as_null: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            to_string_with_config(&vec![1.5, f64::NAN], config).unwrap(),
+            "[1.5,NULL]"
+        );
+    }
+
     #[test]
     fn test_simple_strings() {
         assert_eq!(to_string(&"foo").unwrap(), r#""foo""#);
     }
 
     #[test]
-    fn test_simple_bytes() {
-        assert_eq!(to_string(Bytes::new(b"foo")).unwrap(), r#"b"\x66\x6f\x6f""#);
+    fn test_string_with() {
+        let wrapped = to_string_with(&42, |s| format!("SELECT ({}) ;", s)).unwrap();
+        assert_eq!(wrapped, "SELECT (42) ;");
     }
 
     #[test]
-    fn test_optional_none() {
-        let x: Option<u32> = None;
-        assert_eq!(to_string(&x).unwrap(), "NULL");
+    fn test_string_separated() {
+        assert_eq!(to_string_separated(&[1, 2, 3], ";\n").unwrap(), "1;\n2;\n3");
+        assert_eq!(to_string_separated(&["a"], ", ").unwrap(), r#""a""#);
+        assert_eq!(to_string_separated(&Vec::<u32>::new(), ", ").unwrap(), "");
     }
 
     #[test]
-    fn test_vec_simple() {
+    fn test_string_aliased() {
+        #[derive(Serialize)]
+        struct Test {
+            a: u32,
+        }
+
+        let expected = r#"STRUCT(1 AS `a`) AS `data`"#;
+        assert_eq!(to_string_aliased(&Test { a: 1 }, "data").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_simple_bytes() {
+        assert_eq!(to_string(Bytes::new(b"foo")).unwrap(), r#"b"\x66\x6f\x6f""#);
+    }
+
+    #[test]
+    fn test_check_and_infer() {
+        #[derive(Serialize)]
+        struct Test {
+            a: u32,
+            b: &'static str,
+        }
+
+        let expected = Type::Struct(vec![
+            Field::with_type_and_name(Type::Any, Some("a".to_string())),
+            Field::with_type_and_name(Type::String(None), Some("b".to_string())),
+        ]);
+        let inferred = Type::Struct(vec![
+            Field::with_type_and_name(Type::Int64, Some("a".to_string())),
+            Field::with_type_and_name(Type::String(None), Some("b".to_string())),
+        ]);
+        let test = Test { a: 1, b: "x" };
+        assert_eq!(check_and_infer(&test, &expected).unwrap(), inferred);
+        assert!(check_and_infer(&test, &Type::any_array()).is_err());
+    }
+
+    #[test]
+    fn test_numeric_slices() {
+        for v in [vec![], vec![1], vec![-1, 0, i64::MAX]] {
+            assert_eq!(to_string_i64_slice(&v).unwrap(), to_string(&v).unwrap());
+        }
+        for v in [vec![], vec![1.5], vec![f64::NAN, -0.25, f64::INFINITY]] {
+            assert_eq!(to_string_f64_slice(&v).unwrap(), to_string(&v).unwrap());
+        }
+
+        let v = vec![1.5, f64::NAN];
+        let mut slice_serializer = super::Serializer::new(Vec::new());
+        let mut generic_serializer = super::Serializer::new(Vec::new());
+        assert_eq!(
+            slice_serializer.serialize_f64_slice(&v).unwrap(),
+            v.serialize(&mut generic_serializer).unwrap()
+        );
+
+        // the seq path allocates for every element, the slice path a fixed number of times
+        let ints: Vec<i64> = (0..1000).collect();
+        let floats: Vec<f64> = (0..1000).map(|i| f64::from(i) / 8.0).collect();
+        let allocations = |f: &dyn Fn(&mut super::Serializer<Vec<u8>>)| {
+            let mut serializer = super::Serializer::new(Vec::with_capacity(1 << 16));
+            count_allocations(|| f(&mut serializer)).1
+        };
+        assert!(allocations(&|s| drop(s.serialize_i64_slice(&ints).unwrap())) <= 2);
+        assert!(allocations(&|s| drop(ints.serialize(s).unwrap())) >= ints.len());
+        assert!(allocations(&|s| drop(s.serialize_f64_slice(&floats).unwrap())) <= 2);
+        assert!(allocations(&|s| drop(floats.serialize(s).unwrap())) >= floats.len());
+    }
+
+    #[test]
+    fn test_json_emit_mode() {
+        use crate::wrappers::Json;
+
+        let json = Json::new(r#"{"a":"it's"}"#);
+        let with_mode = |json_emit_mode| {
+            let config = Config {
+                json_emit_mode,
+                ..Config::default()
+            };
+            to_string_with_config(&json, config).unwrap()
+        };
+        assert_eq!(to_string(&json).unwrap(), r#"JSON '{"a":"it\'s"}'"#);
+        assert_eq!(
+            with_mode(JsonEmitMode::JsonLiteral),
+            r#"JSON '{"a":"it\'s"}'"#
+        );
+        assert_eq!(
+            with_mode(JsonEmitMode::ParseJson),
+            r#"PARSE_JSON('{"a":"it\'s"}')"#
+        );
+        assert_eq!(
+            with_mode(JsonEmitMode::CastAsJson),
+            r#"CAST('{"a":"it\'s"}' AS JSON)"#
+        );
+
+        let mut serializer = super::Serializer::new(Vec::new());
+        assert_eq!(json.serialize(&mut serializer).unwrap(), Type::Json);
+    }
+
+    #[test]
+    fn test_json_payloads() {
+        use crate::wrappers::Json;
+
+        #[derive(Serialize)]
+        struct Row {
+            id: u32,
+            payload: Json<'static>,
+        }
+
+        // the contents aren't looked into, arrays and nested quotes stay as they are
+        assert_eq!(
+            to_string_with_type(&Json::new(r#"[1,"two",{"three":3}]"#)).unwrap(),
+            (r#"JSON '[1,"two",{"three":3}]'"#.to_string(), Type::Json)
+        );
+        assert_eq!(
+            to_string_with_type(&vec![Row {
+                id: 1,
+                payload: Json::new(r#"{"a":{"b":"c"}}"#),
+            }])
+            .unwrap(),
+            (
+                r#"[STRUCT(1 AS `id`,JSON '{"a":{"b":"c"}}' AS `payload`)]"#.to_string(),
+                Type::Array(Box::new(Type::Struct(vec![
+                    Field::with_type_and_name(Type::Int64, Some("id".to_string())),
+                    Field::with_type_and_name(Type::Json, Some("payload".to_string())),
+                ])))
+            )
+        );
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_json_from_value() {
+        use crate::wrappers::Json;
+
+        let object = serde_json::json!({"name": "O'Brien", "tags": ["a"]});
+        assert_eq!(
+            to_string(&Json::from(&object)).unwrap(),
+            r#"JSON '{"name":"O\'Brien","tags":["a"]}'"#
+        );
+        let array = serde_json::json!([1, null, {"a": true}]);
+        assert_eq!(
+            to_string(&Json::from(&array)).unwrap(),
+            r#"JSON '[1,null,{"a":true}]'"#
+        );
+        // unlike serializing the value itself
+        assert_eq!(
+            to_string_from_json(&object).unwrap(),
+            r#"STRUCT("O'Brien" AS `name`,["a"] AS `tags`)"#
+        );
+    }
+
+    #[test]
+    fn test_bigquery_float_format() {
+        let values = vec![1e20, 0.00001, 1.5, f64::NAN];
+        assert_eq!(
+            to_string(&values).unwrap(),
+            "[100000000000000000000,0.00001,1.5,CAST('NaN' AS FLOAT64)]"
+        );
+        let config = Config {
+            bigquery_float_format: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            to_string_with_config(&values, config).unwrap(),
+            "[1e+20,1e-05,1.5,CAST('NaN' AS FLOAT64)]"
+        );
+    }
+
+    #[test]
+    fn test_empty_string_as_null() {
+        let values = vec!["", "a"];
+        assert_eq!(
+            to_string_with_type(&values).unwrap(),
+            (
+                r#"["","a"]"#.to_string(),
+                Type::Array(Box::new(Type::String(None)))
+            )
+        );
+
+        let config = Config {
+            empty_string_as_null: true,
+            ..Config::default()
+        };
+        let mut serializer = super::Serializer::with_config(Vec::new(), config.clone());
+        assert_eq!(
+            values.serialize(&mut serializer).unwrap(),
+            Type::Array(Box::new(Type::String(None)))
+        );
+        assert_eq!(
+            String::from_utf8(serializer.writer).unwrap(),
+            r#"[NULL,"a"]"#
+        );
+        // still a string, so it doesn't fit where a number is expected
+        let mut serializer = super::Serializer::with_config(Vec::new(), config);
+        let mut seq = serializer.serialize_seq(None).unwrap();
+        seq.serialize_element(&1).unwrap();
+        assert!(matches!(
+            seq.serialize_element(""),
+            Err(Error::UnexpectedType { .. })
+        ));
+    }
+
+    #[test]
+    fn test_detect_timestamps() {
+        let config = Config {
+            detect_timestamps: true,
+            ..Config::default()
+        };
+        let mut serializer = super::Serializer::with_config(Vec::new(), config.clone());
+        let t = ["2024-01-02T03:04:05Z", "2024-01-02"].serialize(&mut serializer);
+        assert_eq!(
+            t.unwrap(),
+            Type::Struct(vec![
+                Field::with_type_and_name(Type::Timestamp, None),
+                Field::with_type_and_name(Type::String(None), None),
+            ])
+        );
+        assert_eq!(
+            serializer.take_warnings(),
+            vec![Warning::CoercedStringToTimestamp {
+                path: String::new()
+            }]
+        );
+        assert_eq!(
+            String::from_utf8(serializer.writer).unwrap(),
+            r#"STRUCT(TIMESTAMP '2024-01-02T03:04:05Z',"2024-01-02")"#
+        );
+        assert_eq!(
+            to_string(&"2024-01-02T03:04:05Z").unwrap(),
+            r#""2024-01-02T03:04:05Z""#
+        );
+
+        // arrays mixing timestamps with other strings stay arrays of strings
+        let mut serializer = super::Serializer::with_config(Vec::new(), config.clone());
+        let t = vec!["2024-01-02T03:04:05Z", "soon"].serialize(&mut serializer);
+        assert_eq!(t.unwrap(), Type::Array(Box::new(Type::String(None))));
+        assert!(serializer.take_warnings().is_empty());
+        assert_eq!(
+            String::from_utf8(serializer.writer).unwrap(),
+            r#"["2024-01-02T03:04:05Z","soon"]"#
+        );
+        let mut first = std::collections::BTreeMap::new();
+        first.insert("at", "2024-01-02T03:04:05Z");
+        let mut second = std::collections::BTreeMap::new();
+        second.insert("at", "soon");
+        assert_eq!(
+            to_string_with_config(&vec![first, second], config).unwrap(),
+            r#"[STRUCT("2024-01-02T03:04:05Z" AS `at`),STRUCT("soon" AS `at`)]"#
+        );
+    }
+
+    #[test]
+    fn test_numbers_as_strings() {
+        let config = Config {
+            numbers_as_strings: true,
+            ..Config::default()
+        };
+        let mut serializer = super::Serializer::with_config(Vec::new(), config.clone());
+        assert_eq!(42.serialize(&mut serializer).unwrap(), Type::String(None));
+        assert_eq!(String::from_utf8(serializer.writer).unwrap(), r#""42""#);
+        assert_eq!(
+            to_string_with_config(&(1u8, -2i64, 1.5f32), config.clone()).unwrap(),
+            r#"STRUCT("1","-2","1.5")"#
+        );
+        assert_eq!(
+            to_string_with_config(&vec![f64::NAN], config).unwrap(),
+            r#"["NaN"]"#
+        );
+    }
+
+    #[test]
+    fn test_borrowed_options() {
+        fn check<T: Serialize>(value: T, expected: &str, expected_type: Type) {
+            let mut serializer = super::Serializer::new(Vec::new());
+            assert_eq!(value.serialize(&mut serializer).unwrap(), expected_type);
+            assert_eq!(String::from_utf8(serializer.writer).unwrap(), expected);
+        }
+
+        check(Some("a"), r#""a""#, Type::String(None));
+        check(None::<&str>, "NULL", Type::Any);
+        check::<&Option<u32>>(&Some(1), "1", Type::Int64);
+        check::<&Option<u32>>(&None, "NULL", Type::Any);
+        check(Some(Box::<str>::from("b")), r#""b""#, Type::String(None));
+        check(None::<Box<str>>, "NULL", Type::Any);
+        check(Some(&Some(&2u8)), "2", Type::Int64);
+    }
+
+    #[test]
+    fn test_forbid_any() {
+        #[derive(Serialize)]
+        struct Test {
+            a: Option<u32>,
+        }
+
+        let mut config = Config {
+            forbid_any: true,
+            ..Config::default()
+        };
+        assert!(matches!(
+            to_string_with_config(&None::<u32>, config.clone()),
+            Err(Error::UnresolvedType(Type::Any))
+        ));
+        assert!(to_string_with_config(&Test { a: None }, config.clone()).is_err());
+        assert!(to_string_with_config(&vec![None, Some(1)], config.clone()).is_err());
+        assert_eq!(
+            to_string_with_config(&vec![Some(1), None], config.clone()).unwrap(),
+            "[1,NULL]"
+        );
+
+        config.type_hints.insert("a".to_string(), Type::Int64);
+        assert_eq!(
+            to_string_with_config(&Test { a: None }, config).unwrap(),
+            "STRUCT(NULL AS `a`)"
+        );
+    }
+
+    #[test]
+    fn test_decimal_wrappers() {
+        use crate::wrappers::{BigNumeric, Numeric};
+
+        let mut serializer = super::Serializer::new(Vec::new());
+        assert_eq!(
+            Numeric::new("-12.50").serialize(&mut serializer).unwrap(),
+            Type::Numeric(None)
+        );
+        assert_eq!(
+            String::from_utf8(serializer.writer).unwrap(),
+            "NUMERIC '-12.50'"
+        );
+        assert_eq!(
+            to_string(&BigNumeric::new("1e40")).unwrap(),
+            "BIGNUMERIC '1e40'"
+        );
+        for invalid in ["NaN", "1.2.3", ""] {
+            assert!(matches!(
+                to_string(&Numeric::new(invalid)),
+                Err(Error::InvalidDecimal(_))
+            ));
+        }
+        assert_eq!(
+            to_string(&Numeric::new("1.2.3")).unwrap_err().to_string(),
+            r#"invalid decimal: "1.2.3""#
+        );
+    }
+
+    #[test]
+    fn test_numeric_array() {
+        use crate::wrappers::Numeric;
+
+        let prices = vec![
+            Numeric::new("19.99"),
+            Numeric::new("-0.01"),
+            Numeric::new("7"),
+        ];
+        let mut serializer = super::Serializer::new(Vec::new());
+        assert_eq!(
+            prices.serialize(&mut serializer).unwrap(),
+            Type::Array(Box::new(Type::Numeric(None)))
+        );
+        assert_eq!(
+            String::from_utf8(serializer.writer).unwrap(),
+            "[NUMERIC '19.99',NUMERIC '-0.01',NUMERIC '7']"
+        );
+        assert!(matches!(
+            to_string(&vec![Numeric::new("1"), Numeric::new("1.2.3")]),
+            Err(Error::InvalidDecimal(ref s)) if s == "1.2.3"
+        ));
+    }
+
+    #[test]
+    fn test_date_wrapper() {
+        use crate::wrappers::Date;
+
+        #[derive(Serialize)]
+        struct Event {
+            id: u32,
+            day: Date<'static>,
+        }
+
+        assert_eq!(
+            to_string(&vec![Date::new("2024-01-02"), Date::new("2024-02-29")]).unwrap(),
+            "[DATE '2024-01-02',DATE '2024-02-29']"
+        );
+        assert_eq!(
+            to_string(&Event {
+                id: 1,
+                day: Date::new("2024-01-02")
+            })
+            .unwrap(),
+            "STRUCT(1 AS `id`,DATE '2024-01-02' AS `day`)"
+        );
+        assert!(matches!(
+            to_string(&vec![Some(Date::new("2024-01-02")), None]),
+            Ok(ref s) if s == "[DATE '2024-01-02',NULL]"
+        ));
+        assert!(matches!(
+            to_string(&Date::new("01/02/2024")),
+            Err(Error::InvalidDate(_))
+        ));
+        assert!(matches!(
+            to_string(&Date::new("")),
+            Err(Error::InvalidDate(_))
+        ));
+
+        /// Carries a number where the wrapped string is expected.
+        struct NumericDate;
+
+        impl Serialize for NumericDate {
+            fn serialize<S: Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                serializer.serialize_newtype_struct(DATE_TOKEN, &20240102)
+            }
+        }
+        assert!(matches!(
+            to_string(&NumericDate),
+            Err(Error::InvalidLiteralType(Type::Int64))
+        ));
+    }
+
+    #[test]
+    fn test_homogeneous_tuple_as_array() {
+        let config = Config {
+            homogeneous_tuple_as_array: true,
+            ..Config::default()
+        };
+        let mut serializer = super::Serializer::with_config(Vec::new(), config.clone());
+        assert_eq!(
+            (1, 2.5, 3).serialize(&mut serializer).unwrap(),
+            Type::Array(Box::new(Type::Float64))
+        );
+        assert_eq!(String::from_utf8(serializer.writer).unwrap(), "[1,2.5,3]");
+        assert_eq!(
+            to_string_with_config(&[(1, "a"), (2, "b")], config.clone()).unwrap(),
+            r#"[STRUCT(1,"a"),STRUCT(2,"b")]"#
+        );
+        assert_eq!(
+            to_string_with_config(&(vec![1], vec![2]), config.clone()).unwrap(),
+            "STRUCT([1],[2])"
+        );
+        #[derive(Serialize)]
+        struct Pair {
+            a: u32,
+            b: u32,
+        }
+        assert_eq!(
+            to_string_with_config(&Pair { a: 1, b: 2 }, config.clone()).unwrap(),
+            "STRUCT(1 AS `a`,2 AS `b`)"
+        );
+        assert_eq!(
+            to_string_with_config(
+                &(1, "a"),
+                Config {
+                    typed_structs: true,
+                    ..config
+                }
+            )
+            .unwrap(),
+            r#"STRUCT<INT64, STRING>(1,"a")"#
+        );
+    }
+
+    #[test]
+    fn test_homogeneous_tuple_in_array() {
+        let config = Config {
+            homogeneous_tuple_as_array: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            to_string_with_config(&vec![(1, 2), (3, 4)], config.clone()).unwrap(),
+            "[STRUCT(1,2),STRUCT(3,4)]"
+        );
+        assert_eq!(
+            to_string_with_config(&vec![Some((1, 2))], config.clone()).unwrap(),
+            "[STRUCT(1,2)]"
+        );
+        // only direct elements, tuples nested deeper are arrays again
+        #[derive(Serialize)]
+        struct Row {
+            scores: Vec<u32>,
+            pair: (u32, u32),
+        }
+        assert_eq!(
+            to_string_with_config(
+                &vec![Row {
+                    scores: vec![1],
+                    pair: (2, 3)
+                }],
+                config.clone()
+            )
+            .unwrap(),
+            "[STRUCT([1] AS `scores`,[2,3] AS `pair`)]"
+        );
+        assert_eq!(
+            to_string_with_config(
+                &vec![(1, 2)],
+                Config {
+                    nested_arrays: NestedArrays::WrapInStruct,
+                    ..config.clone()
+                }
+            )
+            .unwrap(),
+            "[STRUCT(1,2)]"
+        );
+
+        // elements of tuples expected to be structs are type checked
+        let expected = Type::Struct(vec![
+            Field::with_type_and_name(Type::Int64, None),
+            Field::with_type_and_name(Type::Bool, None),
+        ]);
+        let mut serializer = super::Serializer::with_config(Vec::new(), config);
+        let result = (1, 2).serialize(&mut TypedSerializer::with_serializer(
+            &mut serializer,
+            &expected,
+        ));
+        assert!(matches!(
+            result,
+            Err(Error::UnexpectedType {
+                expected: Type::Bool,
+                found: Type::Int64
+            })
+        ));
+    }
+
+    #[test]
+    fn test_typed_null() {
+        let config = Config {
+            typed_null: true,
+            ..Config::default()
+        };
+        let mut serializer = super::Serializer::with_config(Vec::new(), config.clone());
+        let mut typed_serializer = TypedSerializer::with_serializer(&mut serializer, &Type::Int64);
+        assert_eq!(
+            Some(()).serialize(&mut typed_serializer).unwrap(),
+            Type::Int64
+        );
+        assert_eq!(
+            String::from_utf8(serializer.writer).unwrap(),
+            "CAST(NULL AS INT64)"
+        );
+
+        assert_eq!(
+            to_string_with_config(&vec![Some(1), None], config.clone()).unwrap(),
+            "[1,CAST(NULL AS INT64)]"
+        );
+        // the type of a leading `NULL` isn't known yet
+        assert_eq!(
+            to_string_with_config(&vec![None, Some(1)], config).unwrap(),
+            "[NULL,1]"
+        );
+    }
+
+    #[test]
+    fn test_typed_null_string() {
+        #[derive(Serialize)]
+        struct Row {
+            name: Option<String>,
+        }
+
+        let config = Config {
+            typed_null: true,
+            ..Config::default()
+        };
+        let mut serializer = super::Serializer::with_config(Vec::new(), config.clone());
+        let mut typed_serializer =
+            TypedSerializer::with_serializer(&mut serializer, &Type::String(None));
+        assert_eq!(
+            None::<String>.serialize(&mut typed_serializer).unwrap(),
+            Type::String(None)
+        );
+        assert_eq!(
+            String::from_utf8(serializer.writer).unwrap(),
+            "CAST(NULL AS STRING)"
+        );
+
+        // the field type is expected from the first row
+        let rows = vec![
+            Row {
+                name: Some("a".to_string()),
+            },
+            Row { name: None },
+        ];
+        assert_eq!(
+            to_string_with_config(&rows, config).unwrap(),
+            r#"[STRUCT("a" AS `name`),STRUCT(CAST(NULL AS STRING) AS `name`)]"#
+        );
+        assert_eq!(
+            to_string(&rows).unwrap(),
+            r#"[STRUCT("a" AS `name`),STRUCT(NULL AS `name`)]"#
+        );
+    }
+
+    #[test]
+    fn test_timestamp_wrappers() {
+        use crate::wrappers::{Datetime, Timestamp};
+
+        #[derive(Serialize)]
+        struct Event {
+            at: Timestamp<'static>,
+            count: i64,
+        }
+
+        let mut serializer = super::Serializer::new(Vec::new());
+        assert_eq!(
+            Event {
+                at: Timestamp::new("2024-01-02 15:04:05+00"),
+                count: 3
+            }
+            .serialize(&mut serializer)
+            .unwrap(),
+            Type::Struct(vec![
+                Field::with_type_and_name(Type::Timestamp, Some("at".to_string())),
+                Field::with_type_and_name(Type::Int64, Some("count".to_string())),
+            ])
+        );
+        assert_eq!(
+            String::from_utf8(serializer.writer).unwrap(),
+            "STRUCT(TIMESTAMP '2024-01-02 15:04:05+00' AS `at`,3 AS `count`)"
+        );
+        assert_eq!(
+            to_string(&vec![
+                Timestamp::new("2024-01-02 15:04:05+00"),
+                Timestamp::new("2024-01-03 00:00:00+00")
+            ])
+            .unwrap(),
+            "[TIMESTAMP '2024-01-02 15:04:05+00',TIMESTAMP '2024-01-03 00:00:00+00']"
+        );
+        assert_eq!(
+            to_string(&Datetime::new("2024-01-02 15:04:05")).unwrap(),
+            "DATETIME '2024-01-02 15:04:05'"
+        );
+        // timestamps and datetimes don't mix in an array
+        let mut serializer = super::Serializer::new(Vec::new());
+        let mut seq = serializer.serialize_seq(None).unwrap();
+        seq.serialize_element(&Timestamp::new("2024-01-02 15:04:05+00"))
+            .unwrap();
+        assert!(matches!(
+            seq.serialize_element(&Datetime::new("2024-01-02 15:04:05")),
+            Err(Error::UnexpectedType { .. })
+        ));
+        assert!(matches!(
+            to_string(&Timestamp::new("")),
+            Err(Error::EmptyLiteral(Type::Timestamp))
+        ));
+    }
+
+    #[test]
+    fn test_geography_wrapper() {
+        use crate::wrappers::Geography;
+
+        #[derive(Serialize)]
+        struct Place {
+            name: &'static str,
+            area: Geography<'static>,
+        }
+
+        assert_eq!(
+            to_string_with_type(&Geography::new("POINT(1 2)")).unwrap(),
+            ("ST_GEOGFROMTEXT('POINT(1 2)')".to_string(), Type::Geography)
+        );
+        assert_eq!(
+            to_string_with_type(&vec![Place {
+                name: "square",
+                area: Geography::new("POLYGON((0 0, 1 0, 1 1, 0 1, 0 0))"),
+            }])
+            .unwrap(),
+            (
+                "[STRUCT(\"square\" AS `name`,ST_GEOGFROMTEXT('POLYGON((0 0, 1 0, 1 1, 0 1, 0 0))') AS `area`)]"
+                    .to_string(),
+                Type::Array(Box::new(Type::Struct(vec![
+                    Field::with_type_and_name(Type::String(None), Some("name".to_string())),
+                    Field::with_type_and_name(Type::Geography, Some("area".to_string())),
+                ])))
+            )
+        );
+        assert_eq!(
+            to_string(&Geography::new("POINT(1 2)') OR ('1")).unwrap(),
+            r"ST_GEOGFROMTEXT('POINT(1 2)\') OR (\'1')"
+        );
+        // geographies don't mix with strings in an array
+        let mut serializer = super::Serializer::new(Vec::new());
+        let mut seq = serializer.serialize_seq(None).unwrap();
+        seq.serialize_element(&Geography::new("POINT(1 2)"))
+            .unwrap();
+        assert!(matches!(
+            seq.serialize_element("POINT(3 4)"),
+            Err(Error::UnexpectedType { .. })
+        ));
+    }
+
+    #[test]
+    fn test_interval_wrapper() {
+        use crate::wrappers::Interval;
+
+        assert_eq!(
+            to_string_with_type(&Interval::new("1-2 3 4:5:6.789", "YEAR", "SECOND")).unwrap(),
+            (
+                "INTERVAL '1-2 3 4:5:6.789' YEAR TO SECOND".to_string(),
+                Type::Interval
+            )
+        );
+        assert_eq!(
+            to_string(&vec![
+                Interval::new("10:20", "hour", "minute"),
+                Interval::new("0:30", "HOUR", "MINUTE")
+            ])
+            .unwrap(),
+            "[INTERVAL '10:20' HOUR TO MINUTE,INTERVAL '0:30' HOUR TO MINUTE]"
+        );
+        assert!(matches!(
+            to_string(&Interval::new("1-2", "YEARS", "MONTH")),
+            Err(Error::InvalidIntervalRange(range)) if range == "YEARS TO MONTH"
+        ));
+        assert!(matches!(
+            to_string(&Interval::new("1-2", "MONTH", "YEAR")),
+            Err(Error::InvalidIntervalRange(range)) if range == "MONTH TO YEAR"
+        ));
+        // the parts aren't split on spaces
+        assert!(matches!(
+            to_string(&Interval::new("", "1", "DAY HOUR")),
+            Err(Error::InvalidIntervalRange(range)) if range == "1 TO DAY HOUR"
+        ));
+        assert_eq!(
+            to_string(&Interval::new("1 2", "DAY", "HOUR")).unwrap(),
+            "INTERVAL '1 2' DAY TO HOUR"
+        );
+        assert_eq!(
+            serde_json::to_string(&Interval::new("1-2", "YEAR", "MONTH")).unwrap(),
+            r#"["YEAR","MONTH","1-2"]"#
+        );
+    }
+
+    #[test]
+    fn test_btreemap_sorted_fields() {
+        let mut map = std::collections::BTreeMap::new();
+        for (key, value) in [("c", 3), ("a", 1), ("b", 2)] {
+            map.insert(key.to_string(), value);
+        }
+        let mut serializer = super::Serializer::new(Vec::new());
+        assert_eq!(
+            map.serialize(&mut serializer).unwrap(),
+            Type::Struct(
+                ["a", "b", "c"]
+                    .iter()
+                    .map(|name| Field::with_type_and_name(Type::Int64, Some(name.to_string())))
+                    .collect()
+            )
+        );
+        assert_eq!(
+            String::from_utf8(serializer.writer).unwrap(),
+            "STRUCT(1 AS `a`,2 AS `b`,3 AS `c`)"
+        );
+        assert_eq!(
+            to_string_with_config(
+                &map,
+                Config {
+                    typed_structs: true,
+                    ..Config::default()
+                }
+            )
+            .unwrap(),
+            "STRUCT<`a` INT64, `b` INT64, `c` INT64>(1,2,3)"
+        );
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_json_integers() {
+        let value: serde_json::Value =
+            serde_json::from_str(r#"{"a": 1, "b": -7, "c": 1.0, "d": [1, 2.5]}"#).unwrap();
+        assert_eq!(
+            to_string_from_json(&value).unwrap(),
+            "STRUCT(1 AS `a`,-7 AS `b`,1 AS `c`,[1,2.5] AS `d`)"
+        );
+        let mut serializer = super::Serializer::new(io::sink());
+        let Type::Struct(fields) = value.serialize(&mut serializer).unwrap() else {
+            panic!("expected a struct");
+        };
+        let types: Vec<_> = fields.into_iter().map(|f| f.field_type).collect();
+        assert_eq!(
+            types,
+            [
+                Type::Int64,
+                Type::Int64,
+                Type::Float64,
+                Type::Array(Box::new(Type::Float64))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unit_repr() {
+        #[derive(Serialize)]
+        struct Marker;
+
+        #[derive(Serialize)]
+        struct Test {
+            a: u32,
+            b: (),
+            c: Marker,
+            d: Option<()>,
+        }
+
+        let value = Test {
+            a: 1,
+            b: (),
+            c: Marker,
+            d: None,
+        };
+        assert_eq!(
+            to_string(&value).unwrap(),
+            "STRUCT(1 AS `a`,NULL AS `b`,NULL AS `c`,NULL AS `d`)"
+        );
+        let config = Config {
+            unit_repr: UnitRepr::Omit,
+            ..Config::default()
+        };
+        assert_eq!(
+            to_string_with_config(&value, config.clone()).unwrap(),
+            "STRUCT(1 AS `a`,NULL AS `d`)"
+        );
+        // outside of struct fields unit is still `NULL`
+        assert_eq!(
+            to_string_with_config(&(1, ()), config.clone()).unwrap(),
+            "STRUCT(1,NULL)"
+        );
+
+        #[derive(Serialize)]
+        struct Empty {
+            a: (),
+        }
+        assert!(matches!(
+            to_string_with_config(&Empty { a: () }, config.clone()),
+            Err(Error::EmptyStruct)
+        ));
+
+        // only units that are the whole value are left out
+        #[derive(Serialize)]
+        struct Wrapped(Marker);
+        let mut map = std::collections::BTreeMap::new();
+        map.insert("a", serde_json::json!(1));
+        map.insert("n", serde_json::json!(null));
+        map.insert("b", serde_json::json!([null]));
+        assert_eq!(
+            to_string_with_config(&(&map, Wrapped(Marker), Some(())), config.clone()).unwrap(),
+            "STRUCT(STRUCT(1 AS `a`,[NULL] AS `b`),NULL,NULL)"
+        );
+        #[derive(Serialize)]
+        struct Newtypes {
+            a: Wrapped,
+            b: Option<Wrapped>,
+        }
+        assert_eq!(
+            to_string_with_config(
+                &Newtypes {
+                    a: Wrapped(Marker),
+                    b: Some(Wrapped(Marker))
+                },
+                config.clone()
+            )
+            .unwrap(),
+            "STRUCT(NULL AS `b`)"
+        );
+        assert_eq!(
+            to_string_with_config(
+                &value,
+                Config {
+                    sort_fields: true,
+                    ..config.clone()
+                }
+            )
+            .unwrap(),
+            "STRUCT(1 AS `a`,NULL AS `d`)"
+        );
+        // expected fields are still there
+        assert_eq!(
+            to_string_with_config(
+                &value,
+                Config {
+                    field_order: Some(vec!["d".to_string(), "b".to_string(), "a".to_string()]),
+                    ..config.clone()
+                }
+            )
+            .unwrap(),
+            "STRUCT(NULL AS `d`,NULL AS `b`,1 AS `a`)"
+        );
+
+        // values are serialized once
+        struct Counted<'c>(&'c std::cell::Cell<usize>);
+        impl ser::Serialize for Counted<'_> {
+            fn serialize<S: ser::Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                self.0.set(self.0.get() + 1);
+                serializer.serialize_unit()
+            }
+        }
+        let count = std::cell::Cell::new(0);
+        let mut map = std::collections::BTreeMap::new();
+        map.insert("a", Counted(&count));
+        map.insert("b", Counted(&count));
+        assert!(matches!(
+            to_string_with_config(&map, config),
+            Err(Error::EmptyStruct)
+        ));
+        assert_eq!(count.get(), 2);
+    }
+
+    #[test]
+    fn test_insert_into() {
+        #[derive(Serialize)]
+        struct Row {
+            id: u32,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            name: Option<&'static str>,
+        }
+
+        let rows = [
+            Row { id: 1, name: None },
+            Row {
+                id: 2,
+                name: Some("b"),
+            },
+        ];
+        assert_eq!(
+            to_insert_into("ds.t", &rows).unwrap(),
+            r#"INSERT INTO `ds.t` (`id`,`name`) VALUES (1,NULL),(2,"b")"#
+        );
+        assert!(to_insert_into("t", &[1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_multi_row_values() {
+        #[derive(Serialize)]
+        struct Row {
+            id: u32,
+            name: &'static str,
+        }
+
+        #[derive(Serialize)]
+        struct Reordered {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            score: Option<f64>,
+            name: &'static str,
+            id: u32,
+        }
+
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum AnyRow {
+            Row(Row),
+            Reordered(Reordered),
+        }
+
+        let rows = [
+            AnyRow::Row(Row { id: 1, name: "a" }),
+            AnyRow::Reordered(Reordered {
+                score: Some(1.5),
+                name: "b",
+                id: 2,
+            }),
+            AnyRow::Reordered(Reordered {
+                score: None,
+                name: "c",
+                id: 3,
+            }),
+        ];
+        assert_eq!(
+            to_multi_row_values(&rows).unwrap(),
+            r#"(1,"a",NULL),(2,"b",1.5),(3,"c",NULL)"#
+        );
+        assert!(to_multi_row_values::<Row>(&[]).is_err());
+        assert!(to_multi_row_values(&[1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_pretty() {
+        #[derive(Serialize)]
+        struct Point {
+            x: i32,
+            tags: Vec<&'static str>,
+        }
+
+        let points = vec![
+            Point {
+                x: 1,
+                tags: vec!["a", "b"],
+            },
+            Point { x: 2, tags: vec![] },
+        ];
+        assert_eq!(
+            to_string(&points).unwrap(),
+            r#"[STRUCT(1 AS `x`,["a","b"] AS `tags`),STRUCT(2 AS `x`,[] AS `tags`)]"#
+        );
+        let mut serializer = super::Serializer::pretty(Vec::new());
+        points.serialize(&mut serializer).unwrap();
+        assert_eq!(
+            String::from_utf8(serializer.writer).unwrap(),
+            r#"[
+  STRUCT(
+    1 AS `x`,
+    [
+      "a",
+      "b"
+    ] AS `tags`
+  ),
+  STRUCT(
+    2 AS `x`,
+    [] AS `tags`
+  )
+]"#
+        );
+
+        let mut serializer = super::Serializer::with_config(
+            Vec::new(),
+            Config {
+                indent: Some(1),
+                typed_structs: true,
+                ..Config::default()
+            },
+        );
+        (1, vec![2.5]).serialize(&mut serializer).unwrap();
+        assert_eq!(
+            String::from_utf8(serializer.writer).unwrap(),
+            "STRUCT<INT64, ARRAY<FLOAT64>>(\n 1,\n [\n  2.5\n ]\n)"
+        );
+    }
+
+    #[test]
+    fn test_bare_struct_parens() {
+        #[derive(Serialize)]
+        struct Test {
+            a: u32,
+            b: &'static str,
+            c: (u32,),
+        }
+
+        let value = Test {
+            a: 1,
+            b: "x",
+            c: (2,),
+        };
+        assert_eq!(
+            to_string(&value).unwrap(),
+            r#"STRUCT(1 AS `a`,"x" AS `b`,STRUCT(2) AS `c`)"#
+        );
+        let config = Config {
+            bare_struct_parens: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            to_string_with_config(&value, config.clone()).unwrap(),
+            r#"(1,"x",STRUCT(2))"#
+        );
+        assert_eq!(
+            to_string_with_config(&vec![(1, 2.5), (2, 3.5)], config.clone()).unwrap(),
+            "[(1,2.5),(2,3.5)]"
+        );
+        // the field names aren't written so they're not in the type either
+        let mut serializer = super::Serializer::with_config(Vec::new(), config.clone());
+        assert_eq!(
+            value.serialize(&mut serializer).unwrap(),
+            Type::Struct(vec![
+                Field::with_type_and_name(Type::Int64, None),
+                Field::with_type_and_name(Type::String(None), None),
+                Field::with_type_and_name(
+                    Type::Struct(vec![Field::with_type_and_name(Type::Int64, None)]),
+                    None
+                ),
+            ])
+        );
+        assert_eq!(
+            to_string_with_config(&vec![&value, &value], config.clone()).unwrap(),
+            r#"[(1,"x",STRUCT(2)),(1,"x",STRUCT(2))]"#
+        );
+
+        #[derive(Serialize)]
+        struct Single {
+            a: u32,
+        }
+        assert_eq!(
+            to_string_with_config(&Single { a: 1 }, config.clone()).unwrap(),
+            "STRUCT(1 AS `a`)"
+        );
+        assert_eq!(
+            to_string_with_config(
+                &value,
+                Config {
+                    typed_structs: true,
+                    ..config
+                }
+            )
+            .unwrap(),
+            r#"STRUCT<`a` INT64, `b` STRING, `c` STRUCT<INT64>>(1,"x",STRUCT<INT64>(2))"#
+        );
+    }
+
+    #[test]
+    fn test_with_type() {
+        #[derive(Serialize)]
+        struct Test {
+            a: u32,
+            b: Vec<f64>,
+        }
+
+        assert_eq!(
+            to_string_with_type(&Test { a: 1, b: vec![0.5] }).unwrap(),
+            (
+                "STRUCT(1 AS `a`,[0.5] AS `b`)".to_string(),
+                Type::Struct(vec![
+                    Field::with_type_and_name(Type::Int64, Some("a".to_string())),
+                    Field::with_type_and_name(
+                        Type::Array(Box::new(Type::Float64)),
+                        Some("b".to_string())
+                    ),
+                ])
+            )
+        );
+        assert_eq!(
+            to_bytes_with_type(&vec!["a", "b"]).unwrap(),
+            (
+                br#"["a","b"]"#.to_vec(),
+                Type::Array(Box::new(Type::String(None)))
+            )
+        );
+    }
+
+    #[test]
+    fn test_empty_structs() {
+        #[derive(Serialize)]
+        struct Empty {}
+
+        #[derive(Serialize)]
+        struct EmptyTuple();
+
+        let mut output = Vec::new();
+        let mut serializer = super::Serializer::new(&mut output);
+        assert!(matches!(
+            std::collections::HashMap::<String, u32>::new().serialize(&mut serializer),
+            Err(Error::EmptyStruct)
+        ));
+        assert!(matches!(
+            Empty {}.serialize(&mut serializer),
+            Err(Error::EmptyStruct)
+        ));
+        assert!(matches!(
+            EmptyTuple().serialize(&mut serializer),
+            Err(Error::EmptyStruct)
+        ));
+        // nothing gets written before failing
+        assert!(output.is_empty());
+
+        // the `len` hint of the map API alone is enough
+        let mut serializer = super::Serializer::new(Vec::new());
+        assert!(matches!(
+            serializer.serialize_map(Some(0)),
+            Err(Error::EmptyStruct)
+        ));
+        assert!(serializer.writer.is_empty());
+        assert!(serializer.serialize_map(Some(1)).is_ok());
+
+        // all fields skipped at runtime
+        #[derive(Serialize)]
+        struct Skipped {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            a: Option<u32>,
+        }
+        assert!(matches!(
+            to_string(&Skipped { a: None }),
+            Err(Error::EmptyStruct)
+        ));
+    }
+
+    #[test]
+    fn test_redact() {
+        #[derive(Serialize)]
+        struct Login {
+            user: &'static str,
+            password: &'static str,
+            attempts: u32,
+        }
+
+        let config = Config {
+            redact: Some(FieldPredicate::new(|name| name == "password")),
+            ..Config::default()
+        };
+        let mut serializer = super::Serializer::with_config(Vec::new(), config);
+        let login = Login {
+            user: "ada",
+            password: "hunter2",
+            attempts: 3,
+        };
+        assert_eq!(
+            login.serialize(&mut serializer).unwrap(),
+            Type::Struct(vec![
+                Field::with_type_and_name(Type::String(None), Some("user".to_string())),
+                Field::with_type_and_name(Type::String(None), Some("password".to_string())),
+                Field::with_type_and_name(Type::Int64, Some("attempts".to_string())),
+            ])
+        );
+        assert_eq!(
+            String::from_utf8(serializer.writer).unwrap(),
+            r#"STRUCT("ada" AS `user`,"***" AS `password`,3 AS `attempts`)"#
+        );
+    }
+
+    #[test]
+    fn test_value_validator() {
+        #[derive(Serialize)]
+        struct Row {
+            id: u32,
+            tags: Vec<Option<&'static str>>,
+        }
+
+        let config = Config {
+            value_validator: Some(ValueValidator::new(|value_type| match value_type {
+                Type::Any => Err("NULL not allowed".to_string()),
+                _ => Ok(()),
+            })),
+            typed_null: true,
+            ..Config::default()
+        };
+        let row = Row {
+            id: 1,
+            tags: vec![Some("a"), Some("b")],
+        };
+        assert_eq!(
+            to_string_with_config(&row, config.clone()).unwrap(),
+            r#"STRUCT(1 AS `id`,["a","b"] AS `tags`)"#
+        );
+
+        // rejected even though it would be written as `CAST(NULL AS STRING)`
+        let row = Row {
+            id: 1,
+            tags: vec![Some("a"), None],
+        };
+        let mut serializer = super::Serializer::with_config(Vec::new(), config);
+        match row.serialize(&mut serializer) {
+            Err(Error::Message(message)) => assert_eq!(message, "NULL not allowed"),
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert_eq!(serializer.current_path(), "tags");
+
+        // `NULL`s written in place of other values are rejected too
+        let rejects_null = |config: Config| Config {
+            value_validator: Some(ValueValidator::new(|value_type| match value_type {
+                Type::Any => Err("NULL not allowed".to_string()),
+                _ => Ok(()),
+            })),
+            ..config
+        };
+        let config = rejects_null(Config {
+            nonfinite_as_null: true,
+            ..Config::default()
+        });
+        assert_eq!(to_string_with_config(&1.5, config.clone()).unwrap(), "1.5");
+        assert!(matches!(
+            to_string_with_config(&f64::NAN, config),
+            Err(Error::Message(_))
+        ));
+        let config = rejects_null(Config {
+            field_order: Some(vec!["tags".to_string(), "id".to_string()]),
+            ..Config::default()
+        });
+        let row = Row {
+            id: 1,
+            tags: vec![Some("a")],
+        };
+        assert_eq!(
+            to_string_with_config(&row, config.clone()).unwrap(),
+            r#"STRUCT(["a"] AS `tags`,1 AS `id`)"#
+        );
+        #[derive(Serialize)]
+        struct Id {
+            id: u32,
+        }
+        assert!(matches!(
+            to_string_with_config(&Id { id: 1 }, config),
+            Err(Error::Message(_))
+        ));
+        let mut map = std::collections::BTreeMap::new();
+        map.insert("id", 1);
+        assert!(matches!(
+            to_values_row_with_config(&map, &["id", "name"], rejects_null(Config::default())),
+            Err(Error::Message(_))
+        ));
+    }
+
+    #[test]
+    fn test_empty_identifier() {
+        let mut map = std::collections::HashMap::new();
+        map.insert("", 1);
+        assert!(matches!(to_string(&map), Err(Error::EmptyIdentifier)));
+
+        // a name that's empty only after renaming
+        let mut map = std::collections::HashMap::new();
+        map.insert("_", 1);
+        let config = Config {
+            rename_all: Some(CaseStyle::SnakeCase),
+            ..Config::default()
+        };
+        assert!(matches!(
+            to_string_with_config(&map, config),
+            Err(Error::EmptyIdentifier)
+        ));
+    }
+
+    #[test]
+    fn test_empty_typed_struct_array() {
+        #[derive(Serialize)]
+        struct Item {
+            a: u32,
+        }
+
+        #[derive(Serialize)]
+        struct Test {
+            items: Vec<Item>,
+        }
+
+        let item_type = Type::Struct(vec![Field::with_type_and_name(
+            Type::Int64,
+            Some("a".to_string()),
+        )]);
+        let mut config = Config::default();
+        config
+            .type_hints
+            .insert("items".to_string(), Type::Array(Box::new(item_type)));
+        let test = Test { items: vec![] };
+        assert_eq!(
+            to_string_with_config(&test, config.clone()).unwrap(),
+            "STRUCT(ARRAY<STRUCT<`a` INT64>>[] AS `items`)"
+        );
+        let test = Test {
+            items: vec![Item { a: 1 }],
+        };
+        assert_eq!(
+            to_string_with_config(&test, config).unwrap(),
+            "STRUCT([STRUCT(1 AS `a`)] AS `items`)"
+        );
+        assert_eq!(to_string(&Vec::<Item>::new()).unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_max_string_len() {
+        let config = Config {
+            max_string_len: Some(3),
+            ..Config::default()
+        };
+        assert_eq!(
+            to_string_with_config(&"abc", config.clone()).unwrap(),
+            r#""abc""#
+        );
+        assert!(matches!(
+            to_string_with_config(&("a", "abcd"), config),
+            Err(Error::StringTooLong { len: 4 })
+        ));
+    }
+
+    #[test]
+    fn test_error_path() {
+        #[derive(Serialize)]
+        struct Inner {
+            ok: u32,
+            bad: Vec<Vec<u32>>,
+        }
+
+        #[derive(Serialize)]
+        struct Test {
+            a: u32,
+            inner: Inner,
+        }
+
+        let test = Test {
+            a: 1,
+            inner: Inner {
+                ok: 2,
+                bad: vec![vec![3]],
+            },
+        };
+        let config = Config {
+            nested_arrays: NestedArrays::Error,
+            ..Config::default()
+        };
+        let mut serializer = super::Serializer::with_config(Vec::new(), config);
+        assert!(test.serialize(&mut serializer).is_err());
+        assert_eq!(serializer.current_path(), "inner.bad");
+    }
+
+    #[test]
+    fn test_cow_bytes() {
+        // Without `serde_bytes` a byte slice is just a sequence of integers.
+        #[derive(Serialize)]
+        struct Test<'a> {
+            #[serde(with = "serde_bytes")]
+            bytes: Cow<'a, [u8]>,
+            ints: Cow<'a, [u8]>,
+        }
+
+        let test = Test {
+            bytes: Cow::Borrowed(b"ab"),
+            ints: Cow::Owned(vec![1, 2]),
+        };
+        let expected = r#"STRUCT(b"\x61\x62" AS `bytes`,[1,2] AS `ints`)"#;
+        assert_eq!(to_string(&test).unwrap(), expected);
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_json_value() {
+        // object keys are sorted so `Value`'s (sorted) maps keep the document order
+        let documents = [
+            r#"{"a": 1, "b": "x", "c": null}"#,
+            r#"[1, 2.5, -3]"#,
+            r#"{"nested": {"list": [{"x": true}, {"x": false}]}}"#,
+            r#"[9223372036854775807, -9223372036854775808, 1e300]"#,
+        ];
+        for json in documents {
+            let value: serde_json::Value = serde_json::from_str(json).unwrap();
+            let mut serializer = super::Serializer::new(Vec::new());
+            let mut deserializer = serde_json::Deserializer::from_str(json);
+            let transcoded_type =
+                serde_transcode::transcode(&mut deserializer, &mut serializer).unwrap();
+            let transcoded = String::from_utf8(serializer.writer).unwrap();
+
+            let mut serializer = super::Serializer::new(Vec::new());
+            assert_eq!(value.serialize(&mut serializer).unwrap(), transcoded_type);
+            assert_eq!(String::from_utf8(serializer.writer).unwrap(), transcoded);
+        }
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn test_raw_value() {
+        #[derive(Serialize)]
+        struct Test {
+            a: u32,
+            raw: Box<serde_json::value::RawValue>,
+        }
+
+        let test = Test {
+            a: 1,
+            raw: serde_json::value::RawValue::from_string(r#"{"b": [1, 2]}"#.to_string()).unwrap(),
+        };
+        let expected = r#"STRUCT(1 AS `a`,STRUCT([1,2] AS `b`) AS `raw`)"#;
+        assert_eq!(to_string(&test).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_optional_none() {
+        let x: Option<u32> = None;
+        assert_eq!(to_string(&x).unwrap(), "NULL");
+    }
+
+    #[test]
+    fn test_vec_simple() {
         let v = vec![1, 2, 3];
         let expected = r#"[1,2,3]"#;
         assert_eq!(to_string(&v).unwrap(), expected);
     }
 
     #[test]
-    fn test_vec_complex() {
+    fn test_vec_complex() {
+        #[derive(Serialize)]
+        struct Element {
+            a: u32,
+            b: f64,
+        }
+
+        let v = vec![Element { a: 1, b: 2.5 }, Element { a: 3, b: 10.5 }];
+        let expected = r#"[STRUCT(1 AS `a`,2.5 AS `b`),STRUCT(3 AS `a`,10.5 AS `b`)]"#;
+        assert_eq!(to_string(&v).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_vec_complex_single_field() {
+        #[derive(Serialize)]
+        struct Element {
+            a: u32,
+        }
+
+        let v = vec![Element { a: 1 }, Element { a: 3 }];
+        let expected = r#"[STRUCT(1 AS `a`),STRUCT(3 AS `a`)]"#;
+        assert_eq!(to_string(&v).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_unnest() {
+        #[derive(Serialize)]
+        struct Element {
+            a: u32,
+        }
+
+        let v = vec![Element { a: 1 }, Element { a: 3 }];
+        let expected = r#"UNNEST([STRUCT(1 AS `a`),STRUCT(3 AS `a`)])"#;
+        assert_eq!(to_unnest(&v).unwrap(), expected);
+        assert!(to_unnest(&Element { a: 1 }).is_err());
+    }
+
+    #[test]
+    fn test_vec_buffered_field_type_differs() {
+        #[derive(Serialize)]
+        #[serde(untagged)]
+        enum Element {
+            First { a: bool, b: Option<u32> },
+            Second { b: Option<u32>, a: bool },
+        }
+
+        let v = vec![
+            Element::First { a: false, b: None },
+            Element::Second {
+                b: Some(2),
+                a: true,
+            },
+        ];
+        let mut serializer = super::Serializer::new(Vec::new());
+        let v_type = v.serialize(&mut serializer).unwrap();
+        let expected = r#"[STRUCT(FALSE AS `a`,NULL AS `b`),STRUCT(TRUE AS `a`,2 AS `b`)]"#;
+        assert_eq!(String::from_utf8(serializer.writer).unwrap(), expected);
+        assert_eq!(
+            v_type,
+            Type::Array(Box::new(Type::Struct(vec![
+                Field::with_type_and_name(Type::Bool, Some("a".to_string())),
+                Field::with_type_and_name(Type::Int64, Some("b".to_string())),
+            ])))
+        );
+    }
+
+    #[test]
+    fn test_values_row() {
+        #[derive(Serialize)]
+        struct Row {
+            b: Option<&'static str>,
+            a: u32,
+        }
+
+        let row = Row { b: None, a: 1 };
+        let columns = ["a", "b", "c"];
+        assert_eq!(to_values_row(&row, &columns).unwrap(), "(1,NULL,NULL)");
+
+        let config = Config {
+            missing_as_default: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            to_values_row_with_config(&row, &columns, config).unwrap(),
+            "(1,DEFAULT,DEFAULT)"
+        );
+
+        assert!(to_values_row(&42, &columns).is_err());
+        assert!(matches!(
+            to_values_row(&row, &["a"]),
+            Err(Error::UnexpectedStructField(_))
+        ));
+    }
+
+    #[test]
+    fn test_struct() {
+        #[derive(Serialize)]
+        struct Test {
+            int: u32,
+            seq: Vec<&'static str>,
+        }
+
+        let test = Test {
+            int: 1,
+            seq: vec!["a", "b"],
+        };
+        let expected = r#"STRUCT(1 AS `int`,["a","b"] AS `seq`)"#;
+        assert_eq!(to_string(&test).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_nested_arrays() {
+        let v = vec![vec![1, 2], vec![3, 4]];
+        assert_eq!(to_string(&v).unwrap(), "[[1,2],[3,4]]");
+
+        let config = Config {
+            nested_arrays: NestedArrays::Error,
+            ..Config::default()
+        };
+        let mut serializer = super::Serializer::with_config(Vec::new(), config.clone());
+        assert!(matches!(
+            v.serialize(&mut serializer),
+            Err(Error::NestedArrayNotAllowed)
+        ));
+        // nothing of the inner array is written
+        assert_eq!(serializer.writer, b"[");
+        let mut hints = config.clone();
+        hints.type_hints.insert(
+            "a".to_string(),
+            Type::Array(Box::new(Type::Array(Box::new(Type::Int64)))),
+        );
+        hints.typed_null = true;
+        #[derive(Serialize)]
+        struct Test {
+            a: Vec<Option<Vec<i32>>>,
+        }
+        assert!(matches!(
+            to_string_with_config(&Test { a: vec![None] }, hints),
+            Err(Error::NestedArrayNotAllowed)
+        ));
+
+        let config = Config {
+            nested_arrays: NestedArrays::WrapInStruct,
+            ..Config::default()
+        };
+        let expected = r#"[STRUCT([1,2]),STRUCT([3,4])]"#;
+        assert_eq!(to_string_with_config(&v, config).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_best_effort() {
+        use crate::wrappers::Date;
+
+        #[derive(Serialize)]
+        struct Test {
+            a: u32,
+            big: Date<'static>,
+        }
+
+        let test = Test {
+            a: 1,
+            big: Date::new("2024-13-01"),
+        };
+        assert!(to_string(&test).is_err());
+
+        let config = Config {
+            best_effort: true,
+            ..Config::default()
+        };
+        let mut serializer = super::Serializer::with_config(Vec::new(), config);
+        test.serialize(&mut serializer).unwrap();
+        assert_eq!(
+            String::from_utf8(serializer.writer.clone()).unwrap(),
+            "STRUCT(1 AS `a`,NULL AS `big`)"
+        );
+        assert_eq!(
+            serializer.take_warnings(),
+            vec![Warning::ReplacedUnsupportedWithNull {
+                path: "big".to_string(),
+                value: "2024-13-01".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_type_hints() {
+        #[derive(Serialize)]
+        struct Inner {
+            id: u32,
+        }
+
+        #[derive(Serialize)]
+        struct Test {
+            id: u32,
+            inner: Inner,
+        }
+
+        let mut config = Config::default();
+        config
+            .type_hints
+            .insert("id".to_string(), Type::String(None));
+        config
+            .type_hints
+            .insert("inner.id".to_string(), Type::String(None));
+        let test = Test {
+            id: 42,
+            inner: Inner { id: 7 },
+        };
+        let expected = r#"STRUCT("42" AS `id`,STRUCT("7" AS `id`) AS `inner`)"#;
+        assert_eq!(to_string_with_config(&test, config).unwrap(), expected);
+
+        let mut config = Config::default();
+        config.type_hints.insert("inner".to_string(), Type::Bool);
+        assert!(to_string_with_config(&test, config).is_err());
+    }
+
+    #[test]
+    fn test_coercion_warnings() {
         #[derive(Serialize)]
-        struct Element {
+        struct Inner {
+            id: u32,
+            at: &'static str,
+        }
+
+        #[derive(Serialize)]
+        struct Test {
+            inner: Inner,
+        }
+
+        let mut config = Config {
+            detect_timestamps: true,
+            ..Config::default()
+        };
+        config
+            .type_hints
+            .insert("inner.id".to_string(), Type::String(None));
+        let test = Test {
+            inner: Inner {
+                id: 7,
+                at: "2024-01-02T03:04:05Z",
+            },
+        };
+        let mut serializer = super::Serializer::with_config(io::sink(), config);
+        test.serialize(&mut serializer).unwrap();
+        let warnings = serializer.take_warnings();
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::CoercedToString {
+                    path: "inner.id".to_string()
+                },
+                Warning::CoercedStringToTimestamp {
+                    path: "inner.at".to_string()
+                },
+            ]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "value coerced to STRING (at inner.id)"
+        );
+    }
+
+    #[test]
+    fn test_enum_as_oneof_struct() {
+        #[derive(Serialize)]
+        enum E {
+            A(u32),
+            B(String),
+        }
+
+        let mut config = Config::default();
+        config
+            .enum_as_oneof_struct
+            .insert("E".to_string(), vec!["A".to_string(), "B".to_string()]);
+        let v = vec![E::A(5), E::B("x".to_string())];
+        let expected = r#"[STRUCT(5 AS `A`,NULL AS `B`),STRUCT(NULL AS `A`,"x" AS `B`)]"#;
+        let mut serializer = super::Serializer::with_config(Vec::new(), config.clone());
+        assert_eq!(
+            v.serialize(&mut serializer).unwrap(),
+            Type::Array(Box::new(Type::Struct(vec![
+                Field::with_type_and_name(Type::Int64, Some("A".to_string())),
+                Field::with_type_and_name(Type::String(None), Some("B".to_string())),
+            ])))
+        );
+        assert_eq!(String::from_utf8(serializer.writer).unwrap(), expected);
+
+        config
+            .enum_as_oneof_struct
+            .insert("E".to_string(), vec!["A".to_string()]);
+        assert!(to_string_with_config(&E::B("x".to_string()), config.clone()).is_err());
+
+        config
+            .enum_as_oneof_struct
+            .insert("E".to_string(), vec!["A".to_string(), "B".to_string()]);
+        config.forbid_any = true;
+        config.typed_null = true;
+        assert_eq!(to_string_with_config(&v, config.clone()).unwrap(), expected);
+        config
+            .type_hints
+            .insert("B".to_string(), Type::String(None));
+        assert_eq!(
+            to_string_with_config(&E::A(5), config).unwrap(),
+            "STRUCT(5 AS `A`,CAST(NULL AS STRING) AS `B`)"
+        );
+    }
+
+    #[test]
+    fn test_estimated_query_bytes() {
+        #[derive(Serialize)]
+        struct Test {
             a: u32,
-            b: f64,
         }
 
-        let v = vec![Element { a: 1, b: 2.5 }, Element { a: 3, b: 10.5 }];
-        let expected = r#"[STRUCT(1 AS `a`,2.5 AS `b`),STRUCT(3 AS `a`,10.5 AS `b`)]"#;
-        assert_eq!(to_string(&v).unwrap(), expected);
+        let config = Config {
+            typed_structs: true,
+            ..Config::default()
+        };
+        // STRUCT(1 AS `a`) vs STRUCT<`a` INT64>(1)
+        assert_eq!(
+            estimated_query_bytes(&Test { a: 1 }, Config::default()).unwrap(),
+            16
+        );
+        assert_eq!(estimated_query_bytes(&Test { a: 1 }, config).unwrap(), 20);
     }
 
     #[test]
-    fn test_vec_complex_single_field() {
+    fn test_field_order() {
         #[derive(Serialize)]
-        struct Element {
+        struct Inner {
+            y: u32,
+            x: u32,
+        }
+
+        #[derive(Serialize)]
+        struct Test {
+            b: u32,
+            inner: Inner,
+        }
+
+        let config = Config {
+            field_order: Some(vec!["a".to_string(), "inner".to_string(), "b".to_string()]),
+            ..Config::default()
+        };
+        let test = Test {
+            b: 1,
+            inner: Inner { y: 2, x: 3 },
+        };
+        let expected = "STRUCT(NULL AS `a`,STRUCT(2 AS `y`,3 AS `x`) AS `inner`,1 AS `b`)";
+        assert_eq!(
+            to_string_with_config(&test, config.clone()).unwrap(),
+            expected
+        );
+
+        // only structs with named fields are reordered
+        let mut map = std::collections::BTreeMap::new();
+        map.insert("b", 1);
+        assert_eq!(
+            to_string_with_config(&map, config.clone()).unwrap(),
+            "STRUCT(1 AS `b`)"
+        );
+        assert_eq!(
+            to_string_with_config(&(1, "x"), config.clone()).unwrap(),
+            r#"STRUCT(1,"x")"#
+        );
+        assert!(matches!(
+            to_string_with_config(
+                &std::collections::BTreeMap::<&str, u32>::new(),
+                config.clone()
+            ),
+            Err(Error::EmptyStruct)
+        ));
+
+        let config = Config {
+            field_order: Some(vec!["inner".to_string()]),
+            ..config
+        };
+        assert!(to_string_with_config(&test, config).is_err());
+    }
+
+    #[test]
+    fn test_typed_structs() {
+        #[derive(Serialize)]
+        struct Test {
             a: u32,
+            b: Option<&'static str>,
         }
 
-        let v = vec![Element { a: 1 }, Element { a: 3 }];
-        let expected = r#"[STRUCT(1 AS `a`),STRUCT(3 AS `a`)]"#;
-        assert_eq!(to_string(&v).unwrap(), expected);
+        let config = Config {
+            typed_structs: true,
+            ..Config::default()
+        };
+        let test = Test { a: 1, b: Some("x") };
+        let expected = r#"STRUCT<`a` INT64, `b` STRING>(1,"x")"#;
+        assert_eq!(
+            to_string_with_config(&test, config.clone()).unwrap(),
+            expected
+        );
+
+        let test = Test { a: 1, b: None };
+        assert!(matches!(
+            to_string_with_config(&test, config),
+            Err(Error::UnresolvedType(_))
+        ));
     }
 
     #[test]
-    fn test_struct() {
+    fn test_infer_only() {
         #[derive(Serialize)]
         struct Test {
-            int: u32,
-            seq: Vec<&'static str>,
+            a: u32,
+            b: Vec<&'static str>,
+        }
+
+        let config = Config {
+            infer_only: true,
+            ..Config::default()
+        };
+        let mut serializer = super::Serializer::with_config(Vec::new(), config);
+        let test = Test { a: 1, b: vec!["x"] };
+        let test_type = test.serialize(&mut serializer).unwrap();
+        assert!(serializer.writer.is_empty());
+        assert_eq!(
+            test_type,
+            Type::Struct(vec![
+                Field::with_type_and_name(Type::Int64, Some("a".to_string())),
+                Field::with_type_and_name(
+                    Type::Array(Box::new(Type::String(None))),
+                    Some("b".to_string())
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_rename_all() {
+        #[derive(Serialize)]
+        #[allow(non_snake_case)]
+        struct Test {
+            userName: &'static str,
+        }
+
+        let config = Config {
+            rename_all: Some(CaseStyle::SnakeCase),
+            ..Config::default()
+        };
+        let test = Test { userName: "foo" };
+        let expected = r#"STRUCT("foo" AS `user_name`)"#;
+        assert_eq!(to_string_with_config(&test, config).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_rename_all_duplicate() {
+        let mut map = std::collections::BTreeMap::new();
+        map.insert("userName", 1);
+        map.insert("user_name", 2);
+
+        assert!(to_string(&map).is_ok());
+
+        let config = Config {
+            rename_all: Some(CaseStyle::SnakeCase),
+            ..Config::default()
+        };
+        assert!(matches!(
+            to_string_with_config(&map, config),
+            Err(Error::DuplicateStructField(name)) if name == "user_name"
+        ));
+
+        // column names are case insensitive
+        let mut map = std::collections::BTreeMap::new();
+        map.insert("userName", 1);
+        map.insert("username", 2);
+        assert!(matches!(
+            to_string(&map),
+            Err(Error::DuplicateStructField(name)) if name == "username"
+        ));
+
+        let value: serde_json::Value =
+            serde_json::from_str(r#"{"userName":1,"username":2}"#).unwrap();
+        let config = Config {
+            rename_all: Some(CaseStyle::SnakeCase),
+            ..Config::default()
+        };
+        assert!(matches!(
+            to_string_with_config(&value, config),
+            Err(Error::DuplicateStructField(_))
+        ));
+    }
+
+    #[test]
+    fn test_mixed_field_naming() {
+        let mut serializer = super::Serializer::new(Vec::new());
+        let mut struct_serializer = serializer.serialize_struct("Mixed", 2).unwrap();
+        SerializeStruct::serialize_field(&mut struct_serializer, "a", &1).unwrap();
+        assert!(matches!(
+            ser::SerializeTuple::serialize_element(&mut struct_serializer, &2),
+            Err(Error::MixedFieldNaming)
+        ));
+
+        let mut serializer = super::Serializer::new(Vec::new());
+        let mut struct_serializer = serializer.serialize_tuple(2).unwrap();
+        ser::SerializeTuple::serialize_element(&mut struct_serializer, &1).unwrap();
+        assert!(matches!(
+            SerializeStruct::serialize_field(&mut struct_serializer, "b", &2),
+            Err(Error::MixedFieldNaming)
+        ));
+    }
+
+    #[test]
+    fn test_collision_policy() {
+        let mut map = std::collections::BTreeMap::new();
+        map.insert("a b", 1);
+        map.insert("a_b", 2);
+        map.insert("a_b_2", 3);
+        map.insert("aB", 4);
+
+        let config = Config {
+            rename_all: Some(CaseStyle::SnakeCase),
+            ..Config::default()
+        };
+        assert!(matches!(
+            to_string_with_config(&map, config.clone()),
+            Err(Error::DuplicateStructField(name)) if name == "a_b"
+        ));
+        assert_eq!(
+            to_string_with_config(
+                &map,
+                Config {
+                    collision_policy: CollisionPolicy::Suffix,
+                    ..config
+                }
+            )
+            .unwrap(),
+            "STRUCT(1 AS `a_b`,4 AS `a_b_2`,2 AS `a_b_3`,3 AS `a_b_2_2`)"
+        );
+
+        let mut map = std::collections::BTreeMap::new();
+        map.insert("A", 1);
+        map.insert("A_2", 2);
+        map.insert("a", 3);
+        let config = Config {
+            collision_policy: CollisionPolicy::Suffix,
+            ..Config::default()
+        };
+        assert_eq!(
+            to_string_with_config(&map, config).unwrap(),
+            "STRUCT(1 AS `A`,2 AS `A_2`,3 AS `a_3`)"
+        );
+    }
+
+    #[test]
+    fn test_line_width() {
+        #[derive(Serialize)]
+        struct Test {
+            alpha: u32,
+            beta: u32,
+            gamma: u32,
+            delta: Vec<u32>,
         }
 
+        let config = Config {
+            line_width: Some(20),
+            ..Config::default()
+        };
         let test = Test {
-            int: 1,
-            seq: vec!["a", "b"],
+            alpha: 1,
+            beta: 2,
+            gamma: 3,
+            delta: (1..=12).collect(),
         };
-        let expected = r#"STRUCT(1 AS `int`,["a","b"] AS `seq`)"#;
-        assert_eq!(to_string(&test).unwrap(), expected);
+        let expected = "STRUCT(1 AS `alpha`,\n  2 AS `beta`,3 AS `gamma`,\n  [1,2,3,4,5,6,7,8,9,\n    10,11,12] AS `delta`)";
+        assert_eq!(to_string_with_config(&test, config).unwrap(), expected);
     }
 
     #[test]
@@ -379,6 +3477,33 @@ mod test {
         assert!(s.end().is_err());
     }
 
+    #[test]
+    fn test_map_key_twice() {
+        use serde::ser::SerializeMap;
+
+        let mut serializer = super::Serializer::new(io::sink());
+        let mut map_serializer = serializer.serialize_map(None).unwrap();
+        map_serializer.serialize_key("a").unwrap();
+        assert!(matches!(
+            map_serializer.serialize_key("b"),
+            Err(Error::Message(_))
+        ));
+    }
+
+    #[test]
+    fn test_map_value_without_key() {
+        use serde::ser::SerializeMap;
+
+        let mut serializer = super::Serializer::new(io::sink());
+        let mut map_serializer = serializer.serialize_map(None).unwrap();
+        map_serializer.serialize_key("a").unwrap();
+        map_serializer.serialize_value(&1).unwrap();
+        assert!(matches!(
+            map_serializer.serialize_value(&2),
+            Err(Error::Message(_))
+        ));
+    }
+
     #[test]
     fn test_array_type_checking() {
         let mut serializer = super::Serializer::new(io::sink());
@@ -387,6 +3512,18 @@ mod test {
         assert!(seq_serializer.serialize_element("boom").is_err());
     }
 
+    #[test]
+    fn test_array_numeric_widening() {
+        let mut serializer = super::Serializer::new(io::sink());
+        let mut seq_serializer = serializer.serialize_seq(None).unwrap();
+        seq_serializer.serialize_element(&1).unwrap();
+        seq_serializer.serialize_element(&2.5).unwrap();
+        assert_eq!(
+            seq_serializer.end().unwrap(),
+            Type::Array(Box::new(Type::Float64))
+        );
+    }
+
     #[test]
     fn test_array_deeper_type_checking() {
         #[derive(Serialize)]
@@ -406,4 +3543,744 @@ mod test {
             .serialize_element(&Bar { x: "boom" })
             .is_err());
     }
+
+    #[test]
+    fn test_all_null_array_in_typed_struct() {
+        #[derive(Serialize)]
+        struct Row {
+            ids: Vec<Option<u32>>,
+        }
+
+        let rows = vec![
+            Row {
+                ids: vec![Some(1), Some(2)],
+            },
+            Row {
+                ids: vec![None, None],
+            },
+        ];
+        assert_eq!(
+            to_string_with_type(&rows).unwrap(),
+            (
+                "[STRUCT([1,2] AS `ids`),STRUCT([NULL,NULL] AS `ids`)]".to_string(),
+                Type::Array(Box::new(Type::Struct(vec![Field::with_type_and_name(
+                    Type::Array(Box::new(Type::Int64)),
+                    Some("ids".to_string())
+                )])))
+            )
+        );
+
+        let expected = Type::Struct(vec![Field::with_type_and_name(
+            Type::Array(Box::new(Type::Int64)),
+            Some("ids".to_string()),
+        )]);
+        assert_eq!(check_and_infer(&rows[1], &expected).unwrap(), expected);
+
+        let config = Config {
+            typed_null: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            to_string_with_config(&rows, config).unwrap(),
+            "[STRUCT([1,2] AS `ids`),STRUCT([CAST(NULL AS INT64),CAST(NULL AS INT64)] AS `ids`)]"
+        );
+    }
+
+    #[test]
+    fn test_to_string_typed() {
+        #[derive(Serialize)]
+        struct Row {
+            id: u32,
+            tags: Vec<&'static str>,
+        }
+
+        let row = Row {
+            id: 7,
+            tags: vec!["a"],
+        };
+        let expected = Type::Struct(vec![
+            Field::with_type_and_name(Type::Int64, Some("id".to_string())),
+            Field::with_type_and_name(
+                Type::Array(Box::new(Type::String(None))),
+                Some("tags".to_string()),
+            ),
+        ]);
+        assert_eq!(
+            to_string_typed(&row, &expected).unwrap(),
+            "STRUCT(7 AS `id`,[\"a\"] AS `tags`)"
+        );
+        assert_eq!(
+            to_bytes_typed(&row, &expected).unwrap(),
+            b"STRUCT(7 AS `id`,[\"a\"] AS `tags`)".to_vec()
+        );
+
+        let mismatched = Type::Struct(vec![
+            Field::with_type_and_name(Type::Int64, Some("id".to_string())),
+            Field::with_type_and_name(Type::Array(Box::new(Type::Bool)), Some("tags".to_string())),
+        ]);
+        assert!(matches!(
+            to_string_typed(&row, &mismatched),
+            Err(Error::UnexpectedType {
+                expected: Type::Bool,
+                found: Type::String(None)
+            })
+        ));
+        assert!(matches!(
+            to_string_typed(&"x", &Type::Int64),
+            Err(Error::UnexpectedType { .. })
+        ));
+    }
+
+    #[test]
+    fn test_to_string_cast() {
+        #[derive(Serialize)]
+        struct Point {
+            x: i32,
+            visible: Option<bool>,
+        }
+
+        let point_type = Type::Struct(vec![
+            Field::with_type_and_name(Type::Int64, Some("x".to_string())),
+            Field::with_type_and_name(Type::Bool, Some("visible".to_string())),
+        ]);
+        assert_eq!(
+            to_string_cast(
+                &Point {
+                    x: 1,
+                    visible: None
+                },
+                &point_type
+            )
+            .unwrap(),
+            "CAST(STRUCT(1 AS `x`,NULL AS `visible`) AS STRUCT<`x` INT64, `visible` BOOL>)"
+        );
+        assert_eq!(
+            to_string_cast(&None::<i64>, &Type::Int64).unwrap(),
+            "CAST(NULL AS INT64)"
+        );
+        assert_eq!(
+            to_string_cast(&Vec::<f64>::new(), &Type::Array(Box::new(Type::Float64))).unwrap(),
+            "CAST([] AS ARRAY<FLOAT64>)"
+        );
+        assert_eq!(
+            to_string_cast(&1, &Type::Float64).unwrap(),
+            "CAST(1 AS FLOAT64)"
+        );
+
+        assert!(matches!(
+            to_string_cast(&true, &Type::Int64),
+            Err(Error::UnexpectedType { .. })
+        ));
+        assert!(matches!(
+            to_string_cast(&1, &Type::Any),
+            Err(Error::UnresolvedType(Type::Any))
+        ));
+    }
+
+    #[test]
+    fn test_sort_fields() {
+        #[derive(Serialize)]
+        struct Inner {
+            z: u32,
+            a: u32,
+        }
+
+        let config = Config {
+            sort_fields: true,
+            ..Config::default()
+        };
+
+        // `HashMap` iteration order differs between instances, sorted output doesn't
+        let map: std::collections::HashMap<&str, i32> =
+            vec![("delta", 4), ("alpha", 1), ("charlie", 3), ("bravo", 2)]
+                .into_iter()
+                .collect();
+        assert_eq!(
+            to_string_with_config(&map, config.clone()).unwrap(),
+            "STRUCT(1 AS `alpha`,2 AS `bravo`,3 AS `charlie`,4 AS `delta`)"
+        );
+
+        // nested structs are sorted too, later array elements follow the first one
+        assert_eq!(
+            to_string_with_config(
+                &vec![Inner { z: 1, a: 2 }, Inner { z: 3, a: 4 }],
+                config.clone()
+            )
+            .unwrap(),
+            "[STRUCT(2 AS `a`,1 AS `z`),STRUCT(4 AS `a`,3 AS `z`)]"
+        );
+        assert_eq!(
+            to_string_with_config(
+                &vec![Inner { z: 1, a: 2 }],
+                Config {
+                    typed_structs: true,
+                    ..config
+                }
+            )
+            .unwrap(),
+            "[STRUCT<`a` INT64, `z` INT64>(2,1)]"
+        );
+    }
+
+    #[test]
+    fn test_other_sequences() {
+        use std::collections::{LinkedList, VecDeque};
+
+        let deque: VecDeque<i32> = vec![1, 2, 3].into_iter().collect();
+        assert_eq!(
+            to_string_with_type(&deque).unwrap(),
+            ("[1,2,3]".to_string(), Type::Array(Box::new(Type::Int64)))
+        );
+        let list: LinkedList<i32> = vec![4, 5].into_iter().collect();
+        assert_eq!(
+            to_string_with_type(&list).unwrap(),
+            ("[4,5]".to_string(), Type::Array(Box::new(Type::Int64)))
+        );
+
+        assert_eq!(
+            to_string_with_type(&VecDeque::<i32>::new()).unwrap(),
+            ("[]".to_string(), Type::any_array())
+        );
+        assert_eq!(
+            to_string_with_type(&LinkedList::<i32>::new()).unwrap(),
+            ("[]".to_string(), Type::any_array())
+        );
+
+        // element types merge like in a `Vec`
+        let mixed: VecDeque<Option<i32>> = vec![None, Some(1)].into_iter().collect();
+        assert_eq!(
+            to_string_with_type(&mixed).unwrap(),
+            ("[NULL,1]".to_string(), Type::Array(Box::new(Type::Int64)))
+        );
+        let nested: LinkedList<VecDeque<i32>> = vec![VecDeque::new()].into_iter().collect();
+        let config = Config {
+            nested_arrays: NestedArrays::Error,
+            ..Config::default()
+        };
+        assert!(matches!(
+            to_string_with_config(&nested, config),
+            Err(Error::NestedArrayNotAllowed)
+        ));
+    }
+
+    #[test]
+    fn test_enum_variants() {
+        #[derive(Serialize)]
+        enum Shape {
+            Point,
+            Circle(f64),
+            Segment(u32, u32),
+            Rect { w: u32, h: u32 },
+        }
+
+        assert_eq!(to_string(&Shape::Point).unwrap(), r#""Point""#);
+        assert_eq!(
+            to_string_with_type(&Shape::Circle(2.5)).unwrap(),
+            (
+                "STRUCT(2.5 AS `Circle`)".to_string(),
+                Type::Struct(vec![Field::with_type_and_name(
+                    Type::Float64,
+                    Some("Circle".to_string())
+                )])
+            )
+        );
+        assert_eq!(
+            to_string_with_type(&Shape::Segment(1, 2)).unwrap(),
+            (
+                "STRUCT(STRUCT(1,2) AS `Segment`)".to_string(),
+                Type::Struct(vec![Field::with_type_and_name(
+                    Type::Struct(vec![
+                        Field::with_type_and_name(Type::Int64, None),
+                        Field::with_type_and_name(Type::Int64, None),
+                    ]),
+                    Some("Segment".to_string())
+                )])
+            )
+        );
+        assert_eq!(
+            to_string_with_type(&Shape::Rect { w: 3, h: 4 }).unwrap(),
+            (
+                "STRUCT(STRUCT(3 AS `w`,4 AS `h`) AS `Rect`)".to_string(),
+                Type::Struct(vec![Field::with_type_and_name(
+                    Type::Struct(vec![
+                        Field::with_type_and_name(Type::Int64, Some("w".to_string())),
+                        Field::with_type_and_name(Type::Int64, Some("h".to_string())),
+                    ]),
+                    Some("Rect".to_string())
+                )])
+            )
+        );
+
+        // same variants merge in arrays, different ones don't
+        assert_eq!(
+            to_string(&vec![Shape::Rect { w: 1, h: 2 }, Shape::Rect { w: 3, h: 4 }]).unwrap(),
+            "[STRUCT(STRUCT(1 AS `w`,2 AS `h`) AS `Rect`),STRUCT(STRUCT(3 AS `w`,4 AS `h`) AS `Rect`)]"
+        );
+        assert!(to_string(&vec![Shape::Circle(1.0), Shape::Segment(1, 2)]).is_err());
+
+        // paths of fields within a variant include the variant
+        #[derive(Serialize)]
+        enum Nested {
+            Rows { rows: Vec<Vec<u32>> },
+        }
+        let config = Config {
+            nested_arrays: NestedArrays::Error,
+            ..Config::default()
+        };
+        let mut serializer = super::Serializer::with_config(Vec::new(), config);
+        assert!(Nested::Rows {
+            rows: vec![vec![1]]
+        }
+        .serialize(&mut serializer)
+        .is_err());
+        assert_eq!(serializer.current_path(), "Rows.rows");
+
+        assert_eq!(
+            to_string_with_config(
+                &Shape::Rect { w: 3, h: 4 },
+                Config {
+                    indent: Some(2),
+                    ..Config::default()
+                }
+            )
+            .unwrap(),
+            "STRUCT(\n  STRUCT(\n    3 AS `w`,\n    4 AS `h`\n  ) AS `Rect`\n)"
+        );
+
+        // expected types reach the fields of tuple and struct variants
+        let rect_type = |w| {
+            Type::Struct(vec![Field::with_type_and_name(
+                Type::Struct(vec![
+                    Field::with_type_and_name(w, Some("w".to_string())),
+                    Field::with_type_and_name(Type::Int64, Some("h".to_string())),
+                ]),
+                Some("Rect".to_string()),
+            )])
+        };
+        assert_eq!(
+            to_string_typed(&Shape::Rect { w: 3, h: 4 }, &rect_type(Type::Int64)).unwrap(),
+            "STRUCT(STRUCT(3 AS `w`,4 AS `h`) AS `Rect`)"
+        );
+        assert!(matches!(
+            to_string_typed(&Shape::Rect { w: 3, h: 4 }, &rect_type(Type::Bool)),
+            Err(Error::UnexpectedType {
+                expected: Type::Bool,
+                found: Type::Int64
+            })
+        ));
+        assert!(matches!(
+            to_string_typed(&Shape::Rect { w: 3, h: 4 }, &Type::Int64),
+            Err(Error::UnexpectedType { .. })
+        ));
+        assert!(matches!(
+            to_string_cast(&Shape::Segment(1, 2), &Type::Int64),
+            Err(Error::UnexpectedType { .. })
+        ));
+        let segment_type = Type::Struct(vec![Field::with_type_and_name(
+            Type::Struct(vec![Field::with_type_and_name(Type::Int64, None)]),
+            Some("Segment".to_string()),
+        )]);
+        assert!(matches!(
+            to_string_typed(&Shape::Segment(1, 2), &segment_type),
+            Err(Error::UnexpectedStructField(_))
+        ));
+    }
+
+    /// Map serializing its entries in the given order.
+    struct Entries(Vec<(&'static str, i32)>);
+
+    impl Serialize for Entries {
+        fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+            use serde::ser::SerializeMap;
+
+            let mut map = serializer.serialize_map(Some(self.0.len()))?;
+            for (key, value) in &self.0 {
+                map.serialize_entry(key, value)?;
+            }
+            map.end()
+        }
+    }
+
+    #[test]
+    fn test_map_field_order_across_elements() {
+        let rows = vec![
+            Entries(vec![("b", 1), ("a", 2), ("c", 3)]),
+            Entries(vec![("a", 4), ("c", 5), ("b", 6)]),
+            Entries(vec![("c", 7)]),
+            Entries(vec![]),
+        ];
+        assert_eq!(
+            to_string(&rows[..3]).unwrap(),
+            "[STRUCT(1 AS `b`,2 AS `a`,3 AS `c`),STRUCT(6 AS `b`,4 AS `a`,5 AS `c`),\
+             STRUCT(NULL AS `b`,NULL AS `a`,7 AS `c`)]"
+        );
+        // an empty map is still an empty struct
+        assert!(matches!(to_string(&rows), Err(Error::EmptyStruct)));
+        // keys the first element didn't have can't be added
+        assert!(matches!(
+            to_string(&vec![
+                Entries(vec![("a", 1)]),
+                Entries(vec![("a", 2), ("b", 3)])
+            ]),
+            Err(Error::UnexpectedStructField(_))
+        ));
+
+        // `HashMap` elements all follow the order of the first one
+        let maps: Vec<std::collections::HashMap<&str, i32>> = (0..10)
+            .map(|i| vec![("x", i), ("y", i), ("z", i)].into_iter().collect())
+            .collect();
+        let first_order: Vec<&str> = maps[0].keys().copied().collect();
+        let expected = maps
+            .iter()
+            .map(|map| {
+                let fields = first_order
+                    .iter()
+                    .map(|key| format!("{} AS `{}`", map[key], key))
+                    .collect::<Vec<_>>();
+                format!("STRUCT({})", fields.join(","))
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        assert_eq!(to_string(&maps).unwrap(), format!("[{}]", expected));
+    }
+
+    #[test]
+    fn test_null_field_type_from_later_elements() {
+        #[derive(Serialize)]
+        struct Row {
+            a: bool,
+            b: Option<u32>,
+        }
+
+        #[derive(Serialize)]
+        struct Sparse {
+            a: bool,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            b: Option<u32>,
+        }
+
+        let row_type = Type::Array(Box::new(Type::Struct(vec![
+            Field::with_type_and_name(Type::Bool, Some("a".to_string())),
+            Field::with_type_and_name(Type::Int64, Some("b".to_string())),
+        ])));
+        let rows = vec![
+            Row { a: false, b: None },
+            Row {
+                a: true,
+                b: Some(1),
+            },
+        ];
+        assert_eq!(
+            to_string_with_type(&rows).unwrap(),
+            (
+                "[STRUCT(FALSE AS `a`,NULL AS `b`),STRUCT(TRUE AS `a`,1 AS `b`)]".to_string(),
+                row_type.clone()
+            )
+        );
+
+        let rows = vec![
+            Sparse {
+                a: false,
+                b: Some(1),
+            },
+            Sparse { a: true, b: None },
+        ];
+        assert_eq!(
+            to_string_with_type(&rows).unwrap(),
+            (
+                "[STRUCT(FALSE AS `a`,1 AS `b`),STRUCT(TRUE AS `a`,NULL AS `b`)]".to_string(),
+                row_type
+            )
+        );
+    }
+
+    #[test]
+    fn test_io_errors() {
+        #[derive(Serialize)]
+        struct Inner {
+            name: &'static str,
+            scores: Vec<Option<f64>>,
+        }
+
+        #[derive(Serialize)]
+        struct Test {
+            id: u32,
+            inner: Vec<Inner>,
+            pair: (i32, bool),
+            #[serde(with = "serde_bytes")]
+            key: Vec<u8>,
+        }
+
+        let test = Test {
+            id: 1,
+            inner: vec![
+                Inner {
+                    name: "a",
+                    scores: vec![Some(1.5), None],
+                },
+                Inner {
+                    name: "b",
+                    scores: vec![],
+                },
+            ],
+            pair: (-1, true),
+            key: vec![0, 255],
+        };
+        let configs = vec![
+            Config::default(),
+            Config {
+                indent: Some(2),
+                ..Config::default()
+            },
+            Config {
+                line_width: Some(10),
+                ..Config::default()
+            },
+            Config {
+                typed_structs: true,
+                typed_null: true,
+                ..Config::default()
+            },
+            Config {
+                bare_struct_parens: true,
+                sort_fields: true,
+                ..Config::default()
+            },
+        ];
+        for config in configs {
+            let len = to_bytes_with_config(&test, config.clone()).unwrap().len();
+            // every write, wherever it fails (mid-struct, mid-array), surfaces the error
+            for remaining in 0..len {
+                let mut serializer =
+                    super::Serializer::with_config(FailingWriter { remaining }, config.clone());
+                let result = test.serialize(&mut serializer);
+                assert!(
+                    matches!(result, Err(Error::IOError(_))),
+                    "{:?} after {} bytes with {:?}",
+                    result,
+                    remaining,
+                    config
+                );
+            }
+            let mut serializer =
+                super::Serializer::with_config(FailingWriter { remaining: len }, config);
+            assert!(test.serialize(&mut serializer).is_ok());
+        }
+    }
+
+    #[cfg(not(feature = "validate"))]
+    #[test]
+    fn test_validate_output_without_feature() {
+        let config = Config {
+            validate_output: true,
+            ..Config::default()
+        };
+        assert!(matches!(
+            to_string_with_config(&1, config),
+            Err(Error::Message(_))
+        ));
+    }
+
+    #[test]
+    fn test_scratch() {
+        #[derive(Serialize)]
+        struct Row {
+            id: u32,
+            tags: Vec<&'static str>,
+        }
+
+        let config = Config {
+            typed_structs: true,
+            ..Config::default()
+        };
+        let rows: Vec<Row> = (0..100)
+            .map(|id| Row {
+                id,
+                tags: vec!["a", "b"],
+            })
+            .collect();
+        let mut scratch = Scratch::with_config(config.clone());
+        let mut output_ptr = None;
+        let mut spare_ptr = None;
+        for row in &rows {
+            let expected = to_bytes_with_config(row, config.clone()).unwrap();
+            let serialized = to_bytes_with_scratch(row, &mut scratch).unwrap();
+            assert_eq!(serialized, expected.as_slice());
+            // the output buffer is reused once it's big enough
+            if row.id >= 10 {
+                assert_eq!(
+                    *output_ptr.get_or_insert(serialized.as_ptr()),
+                    serialized.as_ptr()
+                );
+            }
+            // so is the buffer of the field written after the whole struct is known
+            assert_eq!(scratch.buffers.len(), 1);
+            let spare = scratch.buffers[0].as_ptr();
+            assert_eq!(*spare_ptr.get_or_insert(spare), spare);
+        }
+        assert_eq!(
+            to_bytes_with_scratch(&rows[1], &mut scratch).unwrap(),
+            br#"STRUCT<`id` INT64, `tags` ARRAY<STRING>>(1,["a","b"])"#.as_slice()
+        );
+
+        let (_, with_scratch) =
+            count_allocations(|| to_bytes_with_scratch(&rows[2], &mut scratch).map(|_| ()));
+        let (_, without_scratch) =
+            count_allocations(|| to_bytes_with_config(&rows[2], config.clone()).map(|_| ()));
+        assert!(with_scratch < without_scratch);
+
+        // field lists are reused too, across values and across array elements
+        to_bytes_with_scratch(&rows, &mut scratch).unwrap();
+        let spare_fields = scratch.fields.iter().map(Vec::as_ptr).collect::<Vec<_>>();
+        assert!(!spare_fields.is_empty());
+        for row in &rows {
+            to_bytes_with_scratch(row, &mut scratch).unwrap();
+        }
+        to_bytes_with_scratch(&rows, &mut scratch).unwrap();
+        let mut reused = scratch.fields.iter().map(Vec::as_ptr).collect::<Vec<_>>();
+        reused.sort();
+        let mut spare_fields = spare_fields;
+        spare_fields.sort();
+        assert_eq!(reused, spare_fields);
+        let (_, with_scratch) = count_allocations(|| {
+            rows.iter()
+                .try_for_each(|row| to_bytes_with_scratch(row, &mut scratch).map(|_| ()))
+        });
+        let (_, without_scratch) = count_allocations(|| {
+            rows.iter()
+                .try_for_each(|row| to_bytes_with_config(row, config.clone()).map(|_| ()))
+        });
+        // at least the field list of every row is saved
+        assert!(with_scratch + rows.len() <= without_scratch);
+
+        assert!(to_bytes_with_scratch(
+            &std::collections::BTreeMap::<String, u32>::new(),
+            &mut scratch
+        )
+        .is_err());
+        assert_eq!(
+            to_bytes_with_scratch(&1, &mut scratch).unwrap(),
+            b"1".as_slice()
+        );
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let s = "say \"hi\"\\\n\t\u{1}";
+        assert_eq!(to_string(&s).unwrap(), r#""say \"hi\"\\\n\t\u0001""#);
+
+        let emoji = "smile \u{1F600}";
+        let combining = "e\u{301}";
+        assert_eq!(to_string(&emoji).unwrap(), "\"smile \u{1F600}\"");
+        assert_eq!(to_string(&combining).unwrap(), "\"e\u{301}\"");
+
+        let config = Config {
+            escape_unicode: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            to_string_with_config(&emoji, config.clone()).unwrap(),
+            r#""smile \U0001f600""#
+        );
+        assert_eq!(
+            to_string_with_config(&combining, config.clone()).unwrap(),
+            r#""e\u0301""#
+        );
+        assert_eq!(
+            to_string_with_config(&s, config.clone()).unwrap(),
+            to_string(&s).unwrap()
+        );
+
+        for value in &[s, emoji, combining] {
+            assert_eq!(
+                crate::from_str::<String>(&to_string(value).unwrap()).unwrap(),
+                *value
+            );
+            assert_eq!(
+                crate::from_str::<String>(&to_string_with_config(value, config.clone()).unwrap())
+                    .unwrap(),
+                *value
+            );
+        }
+    }
+
+    #[test]
+    fn test_128_bit_integers() {
+        assert_eq!(
+            to_string_with_type(&-42i128).unwrap(),
+            ("-42".to_string(), Type::Int64)
+        );
+        assert_eq!(
+            to_string_with_type(&(-(i64::MAX as i128) * 1000)).unwrap(),
+            (
+                "NUMERIC '-9223372036854775807000'".to_string(),
+                Type::Numeric(None)
+            )
+        );
+        // past the 29 integer digits of NUMERIC
+        assert_eq!(
+            to_string_with_type(&u128::MAX).unwrap(),
+            (
+                "BIGNUMERIC '340282366920938463463374607431768211455'".to_string(),
+                Type::BigNumeric
+            )
+        );
+        assert_eq!(
+            to_string_with_type(&i128::MIN).unwrap(),
+            (
+                "BIGNUMERIC '-170141183460469231731687303715884105728'".to_string(),
+                Type::BigNumeric
+            )
+        );
+        assert_eq!(
+            to_string_with_type(&vec![1u128, u64::MAX as u128 * 10]).unwrap(),
+            (
+                "[1,NUMERIC '184467440737095516150']".to_string(),
+                Type::Array(Box::new(Type::Numeric(None)))
+            )
+        );
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let duplicated = Entries(vec![("a", 1), ("b", 2), ("a", 3)]);
+        for config in [
+            Config::default(),
+            Config {
+                sort_fields: true,
+                ..Config::default()
+            },
+            Config {
+                typed_structs: true,
+                ..Config::default()
+            },
+        ] {
+            assert!(matches!(
+                to_string_with_config(&duplicated, config),
+                Err(Error::DuplicateStructField(name)) if name == "a"
+            ));
+        }
+
+        #[derive(Serialize)]
+        struct Extra {
+            id: u32,
+            note: &'static str,
+        }
+
+        #[derive(Serialize)]
+        struct Flattened {
+            id: u32,
+            #[serde(flatten)]
+            extra: Extra,
+        }
+
+        let flattened = Flattened {
+            id: 1,
+            extra: Extra { id: 2, note: "x" },
+        };
+        assert!(matches!(
+            to_string(&flattened),
+            Err(Error::DuplicateStructField(name)) if name == "id"
+        ));
+    }
 }
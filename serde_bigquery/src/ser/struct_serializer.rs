@@ -1,64 +1,323 @@
-use std::{collections::HashMap, io};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    io,
+};
 
 use serde::{ser, Serialize};
 
 use crate::error::{Error, Result};
 use crate::{
     ser::{
-        identifier::{format_as_identifier, to_identifier},
+        config::{CollisionPolicy, UnitRepr},
+        identifier::{suffixed_identifier, to_key_identifier},
         serializer::Serializer,
     },
     types::{Field, Type},
 };
 
+/// Written in place of fields matched by `Config::redact`.
+const REDACTED: &str = "***";
+
 pub struct StructSerializer<'a, W> {
     serializer: &'a mut Serializer<W>,
     fields: Vec<Field>,
+    /// Names of the fields written so far, lowercased as BigQuery column names are case
+    /// insensitive.
+    field_names: HashSet<String>,
+    /// Lowercased keys as given, before `Config::rename_all`: keys differing only in case name
+    /// the same column even when renaming keeps them apart (`userName` and `username`).
+    source_names: HashSet<String>,
+    /// Whether the fields have names, known after the first one. Mixing named and positional
+    /// fields (possible through the raw `SerializeStruct`/`SerializeTuple` API) is rejected.
+    named_fields: Option<bool>,
     pending_key: Option<String>,
     fields_buffer: Option<FieldsBuffer<'a>>,
+    /// Output of the fields when `STRUCT<...>` header has to be written before them.
+    body: Option<Serializer<Vec<u8>>>,
+    /// Set when serializing `serde_json::value::RawValue`, holds the type of its contents.
+    raw_value: Option<Type>,
+    /// Set when serializing a `VALUES` row: `(v1,v2,...)` without field names.
+    row: bool,
+    /// Buffered tuple elements while it's not known if the tuple is written as an array, see
+    /// `Config::homogeneous_tuple_as_array`.
+    tuple_elements: Option<Vec<(Type, Vec<u8>)>>,
+    /// Buffered fields to be sorted by name, see `Config::sort_fields`.
+    sorted_fields: Option<Vec<(Field, Vec<u8>)>>,
+    /// Set for tuple and struct enum variants: the struct is the only field of an outer
+    /// `STRUCT(... AS variant)`.
+    variant: Option<String>,
 }
 
 impl<'a, W> StructSerializer<'a, W> {
-    pub(crate) fn with_serializer(serializer: &'a mut Serializer<W>) -> Self {
+    fn new(serializer: &'a mut Serializer<W>) -> Self {
+        // a unit in the struct isn't the value of the field being serialized
+        serializer.omit_unit = false;
+        let fields = serializer.field_list();
         Self {
             serializer,
-            fields: Vec::new(),
+            fields,
+            field_names: HashSet::new(),
+            source_names: HashSet::new(),
+            named_fields: None,
             pending_key: None,
             fields_buffer: None,
+            body: None,
+            raw_value: None,
+            row: false,
+            tuple_elements: None,
+            sorted_fields: None,
+            variant: None,
         }
     }
 
     pub(crate) fn with_expected_fields(self, expected_fields: &'a [Field]) -> Self {
         Self {
-            fields_buffer: Some(FieldsBuffer::with_expected_fields(expected_fields)),
+            fields_buffer: Some(FieldsBuffer::with_expected_fields(
+                Cow::Borrowed(expected_fields),
+                self.serializer.buffered_fields(),
+            )),
+            sorted_fields: None,
             ..self
         }
     }
 }
 
 impl<'a, W: io::Write> StructSerializer<'a, W> {
-    fn serialize_field<T>(&mut self, key: Option<&str>, value: &T) -> Result<()>
+    pub(crate) fn open(serializer: &'a mut Serializer<W>) -> Result<Self> {
+        Self::open_ordered(serializer, false)
+    }
+
+    /// Like `open` but for structs with named fields, the only ones following
+    /// `Config::field_order`.
+    pub(crate) fn open_struct(serializer: &'a mut Serializer<W>) -> Result<Self> {
+        Self::open_ordered(serializer, true)
+    }
+
+    fn open_ordered(serializer: &'a mut Serializer<W>, ordered: bool) -> Result<Self> {
+        serializer.depth += 1;
+        serializer.array_element = false;
+        if let Some(columns) = serializer.row_columns.take() {
+            serializer.write(b"(")?;
+            serializer.write_line_break()?;
+            return Ok(Self {
+                fields_buffer: Some(FieldsBuffer::with_expected_fields(
+                    Cow::Owned(columns),
+                    serializer.buffered_fields(),
+                )),
+                row: true,
+                ..Self::new(serializer)
+            });
+        }
+
+        // only the outermost structs (not nested in a struct field) follow `field_order`
+        let fields_buffer = match serializer.config.field_order {
+            Some(ref field_order) if ordered && serializer.path.is_empty() => {
+                let expected_fields = field_order
+                    .iter()
+                    .map(|name| Field::with_name(Some(name.clone())))
+                    .collect();
+                Some(FieldsBuffer::with_expected_fields(
+                    Cow::Owned(expected_fields),
+                    serializer.buffered_fields(),
+                ))
+            }
+            _ => None,
+        };
+        let sorted_fields = if serializer.config.sort_fields && fields_buffer.is_none() {
+            Some(Vec::new())
+        } else {
+            None
+        };
+        // the field list is taken before the body buffer borrows the spare ones
+        let mut struct_serializer = Self {
+            fields_buffer,
+            sorted_fields,
+            ..Self::new(serializer)
+        };
+        struct_serializer.body = start_struct(struct_serializer.serializer)?;
+        Ok(struct_serializer)
+    }
+
+    /// Like `open` but for tuples, which may turn out to be arrays when
+    /// `Config::homogeneous_tuple_as_array` is set.
+    pub(crate) fn open_tuple(serializer: &'a mut Serializer<W>) -> Result<Self> {
+        // an array can't be an element of another array
+        if !serializer.config.homogeneous_tuple_as_array
+            || serializer.row_columns.is_some()
+            || serializer.array_element
+        {
+            return Self::open(serializer);
+        }
+        serializer.depth += 1;
+        Ok(Self {
+            tuple_elements: Some(Vec::new()),
+            ..Self::new(serializer)
+        })
+    }
+
+    /// Like `open` but for the fields of an enum variant, externally tagged like in serde_json:
+    /// `STRUCT(STRUCT(1 AS w,2 AS h) AS Rect)`.
+    pub(crate) fn open_variant(serializer: &'a mut Serializer<W>, variant: &str) -> Result<Self> {
+        let variant = serializer.field_name(variant).into_owned();
+        serializer.depth += 1;
+        serializer.write(b"STRUCT(")?;
+        serializer.write_line_break()?;
+        serializer.path.push(variant.clone());
+        let struct_serializer = Self::open(serializer)?;
+        Ok(Self {
+            variant: Some(variant),
+            ..struct_serializer
+        })
+    }
+
+    #[cfg(feature = "serde_json")]
+    pub(crate) fn raw_value(serializer: &'a mut Serializer<W>) -> Self {
+        Self {
+            raw_value: Some(Type::Any),
+            ..Self::new(serializer)
+        }
+    }
+
+    pub(crate) fn serialize_field<T>(&mut self, key: Option<&str>, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        let mut decision = FieldsBufferDecision::Expected;
-        if let Some(ref mut fields_buffer) = self.fields_buffer {
-            decision = fields_buffer.decide(key, value)?;
+        #[cfg(feature = "serde_json")]
+        if self.raw_value.is_some() {
+            self.raw_value = Some(crate::ser::raw_value::transcode(self.serializer, value)?);
+            return Ok(());
         }
 
-        match decision {
-            FieldsBufferDecision::Expected => {
-                if !self.fields.is_empty() {
-                    self.serializer.write(b",")?;
-                }
-                let field_type = self.serializer.serialize(value)?;
+        match self.named_fields {
+            Some(named) if named != key.is_some() => return Err(Error::MixedFieldNaming),
+            _ => self.named_fields = Some(key.is_some()),
+        }
+
+        if let Some(ref mut tuple_elements) = self.tuple_elements {
+            // tuples expected to be structs stay structs, their elements are still checked
+            let expected_type = match self.fields_buffer {
+                Some(ref mut fields_buffer) => fields_buffer.expect_unnamed()?,
+                None => Type::Any,
+            };
+            let mut buffer = self.serializer.buffer();
+            let element_type = buffer.serialize_field(None, value, &expected_type)?;
+            tuple_elements.push((element_type, self.serializer.absorb(buffer)));
+            return Ok(());
+        }
 
-                if let Some(key) = key {
-                    if !key.is_empty() {
-                        self.serializer
-                            .write_fmt(format_args!(" AS {}", format_as_identifier(key)))?;
+        let omit_unit = key.is_some() && self.serializer.config.unit_repr == UnitRepr::Omit;
+        let source_collision = match key {
+            Some(key) => !self.source_names.insert(key.to_ascii_lowercase()),
+            None => false,
+        };
+        let key = key.map(|key| self.serializer.field_name(key));
+        let key = key.as_deref();
+
+        // names are checked after renaming so keys that collapse into one are caught too
+        let key = match key {
+            Some("") => return Err(Error::EmptyIdentifier),
+            Some(key)
+                if source_collision || self.field_names.contains(&key.to_ascii_lowercase()) =>
+            {
+                match self.serializer.config.collision_policy {
+                    CollisionPolicy::Error => {
+                        return Err(Error::DuplicateStructField(key.to_string()))
+                    }
+                    CollisionPolicy::Suffix => {
+                        Some(Cow::Owned(suffixed_identifier(key, &self.field_names)))
                     }
                 }
+            }
+            key => key.map(Cow::Borrowed),
+        };
+        let key = key.as_deref();
+        if let Some(key) = key {
+            self.field_names.insert(key.to_ascii_lowercase());
+        }
+
+        match (key, &self.serializer.config.redact) {
+            (Some(key), Some(redact)) if redact.matches(key) => {
+                self.write_field_value(Some(key), REDACTED, false)
+            }
+            _ => self.write_field_value(key, value, omit_unit),
+        }
+    }
+
+    /// Writes (or buffers) a field whose name has already been checked. With `omit_unit` (see
+    /// `UnitRepr::Omit`) a unit value is left out.
+    fn write_field_value<T>(&mut self, key: Option<&str>, value: &T, omit_unit: bool) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if let Some(ref mut sorted_fields) = self.sorted_fields {
+            let mut buffer = self.serializer.buffer();
+            buffer.omit_unit = omit_unit;
+            let field_type = buffer.serialize_field(key, value, &Type::Any)?;
+            let unit_omitted = buffer.unit_omitted;
+            let serialized = self.serializer.absorb(buffer);
+            if unit_omitted {
+                self.serializer.recycle(serialized);
+                return Ok(());
+            }
+            sorted_fields.push((
+                Field::with_type_and_name(field_type, key.map(|name| name.to_string())),
+                serialized,
+            ));
+            return Ok(());
+        }
+
+        let mut decision = FieldsBufferDecision::Expected(Type::Any);
+        if let Some(ref mut fields_buffer) = self.fields_buffer {
+            decision = fields_buffer.decide(self.serializer, key, value, omit_unit)?;
+        }
+
+        match decision {
+            FieldsBufferDecision::Expected(expected_type) => {
+                let first = self.fields.is_empty();
+                // units are only known to be left out once serialized, so nothing can be
+                // written before that
+                let field_type = if self.row || omit_unit {
+                    let mut buffer = self.serializer.buffer();
+                    buffer.omit_unit = omit_unit;
+                    let mut field_type = buffer.serialize_field(key, value, &expected_type)?;
+                    let unit_omitted = buffer.unit_omitted;
+                    let mut serialized = self.serializer.absorb(buffer);
+                    if unit_omitted {
+                        if self.fields_buffer.is_none() {
+                            self.serializer.recycle(serialized);
+                            return Ok(());
+                        }
+                        // an expected field is still written, like the ones never serialized
+                        self.serializer.validate(&Type::Any)?;
+                        serialized.extend_from_slice(b"NULL");
+                        field_type = expected_type;
+                    }
+                    if self.row {
+                        let value = row_value(self.serializer, &field_type, &serialized);
+                        write_serialized_field(self.serializer, first, None, value)?;
+                    } else {
+                        match self.body {
+                            Some(ref mut body) => {
+                                write_serialized_field(body, first, None, &serialized)?
+                            }
+                            None => {
+                                write_serialized_field(self.serializer, first, key, &serialized)?
+                            }
+                        }
+                    }
+                    self.serializer.recycle(serialized);
+                    field_type
+                } else {
+                    match self.body {
+                        Some(ref mut body) => {
+                            write_field(body, first, key, value, &expected_type, false)?
+                        }
+                        None => {
+                            write_field(self.serializer, first, key, value, &expected_type, true)?
+                        }
+                    }
+                };
 
                 self.fields.push(Field::with_type_and_name(
                     field_type,
@@ -76,35 +335,233 @@ impl<'a, W: io::Write> StructSerializer<'a, W> {
             serializer,
             mut fields,
             fields_buffer,
+            mut body,
+            raw_value,
+            row,
+            tuple_elements,
+            sorted_fields,
+            variant,
             ..
         } = self;
 
-        // serialized potentially buffered fields
-        if let Some(fields_buffer) = fields_buffer {
-            for (field, serialized) in fields_buffer.drain() {
-                if !fields.is_empty() {
-                    serializer.write(b",")?;
+        if let Some(raw_value_type) = raw_value {
+            return Ok(raw_value_type);
+        }
+
+        if let Some(tuple_elements) = tuple_elements {
+            // tuples expected to be structs stay structs
+            if fields_buffer.is_none() {
+                if let Some(element_type) = homogeneous_type(&tuple_elements) {
+                    return write_array(serializer, tuple_elements, element_type);
+                }
+            }
+            body = start_struct(serializer)?;
+            for (element_type, serialized) in tuple_elements {
+                let first = fields.is_empty();
+                match body {
+                    Some(ref mut body) => write_serialized_field(body, first, None, &serialized)?,
+                    None => write_serialized_field(serializer, first, None, &serialized)?,
                 }
-                serializer.write(&serialized)?;
+                serializer.recycle(serialized);
+                fields.push(Field::with_type_and_name(element_type, None));
+            }
+        }
 
-                if let Some(ref key) = field.field_name {
-                    if !key.is_empty() {
-                        serializer.write_fmt(format_args!(" AS {}", format_as_identifier(key)))?;
+        // serialized potentially buffered fields
+        let buffered_fields = match (fields_buffer, sorted_fields) {
+            (Some(fields_buffer), _) => Some(fields_buffer.drain(serializer)?),
+            (None, Some(mut sorted_fields)) => {
+                sorted_fields.sort_by(|(a, _), (b, _)| a.field_name.cmp(&b.field_name));
+                Some(sorted_fields)
+            }
+            (None, None) => None,
+        };
+        if let Some(buffered_fields) = buffered_fields {
+            for (field, serialized) in buffered_fields {
+                let first = fields.is_empty();
+                match body {
+                    _ if row => {
+                        let serialized = row_value(serializer, &field.field_type, &serialized);
+                        write_serialized_field(serializer, first, None, serialized)?
                     }
+                    Some(ref mut body) => write_serialized_field(body, first, None, &serialized)?,
+                    None => write_serialized_field(
+                        serializer,
+                        first,
+                        field.field_name.as_deref(),
+                        &serialized,
+                    )?,
                 }
+                serializer.recycle(serialized);
 
-                fields.push(field.clone());
+                fields.push(field);
             }
         }
 
+        // BigQuery has no zero-field structs
         if fields.is_empty() {
-            Err(Error::EmptyStruct)
-        } else {
-            serializer.write(b")").map(|_| Type::Struct(fields))
+            return Err(Error::EmptyStruct);
+        }
+        let struct_type = match body {
+            Some(body) => {
+                let body = serializer.absorb(body);
+                let struct_type = if serializer.config.typed_structs {
+                    let struct_type = Type::Struct(fields);
+                    if struct_type.contains_any() {
+                        return Err(Error::UnresolvedType(struct_type));
+                    }
+                    serializer.write_fmt(format_args!("{}(", struct_type))?;
+                    serializer.write_line_break()?;
+                    serializer.write(&body)?;
+                    struct_type
+                } else if fields.len() == 1 {
+                    // `(x)` would be just a parenthesized value
+                    serializer.write(b"STRUCT(")?;
+                    serializer.write_line_break()?;
+                    serializer.write(&body)?;
+                    write_field_name(serializer, fields[0].field_name.as_deref())?;
+                    Type::Struct(fields)
+                } else {
+                    serializer.write(b"(")?;
+                    serializer.write_line_break()?;
+                    serializer.write(&body)?;
+                    // field names aren't written so they're not part of the type either
+                    Type::Struct(
+                        fields
+                            .into_iter()
+                            .map(|field| Field {
+                                field_name: None,
+                                ..field
+                            })
+                            .collect(),
+                    )
+                };
+                serializer.recycle(body);
+                struct_type
+            }
+            None => Type::Struct(fields),
+        };
+
+        serializer.depth -= 1;
+        serializer.write_line_break()?;
+        serializer.write(b")")?;
+
+        match variant {
+            Some(variant) => {
+                serializer.path.pop();
+                write_field_name(serializer, Some(&variant))?;
+                serializer.depth -= 1;
+                serializer.write_line_break()?;
+                serializer.write(b")").map(|_| {
+                    Type::Struct(vec![Field::with_type_and_name(struct_type, Some(variant))])
+                })
+            }
+            None => Ok(struct_type),
         }
     }
 }
 
+/// Writes `STRUCT(` unless `Config::typed_structs` or `Config::bare_struct_parens` is set, in
+/// which case fields go to the returned buffer until the header can be written.
+fn start_struct<W: io::Write>(
+    serializer: &mut Serializer<W>,
+) -> Result<Option<Serializer<Vec<u8>>>> {
+    if serializer.config.typed_structs || serializer.config.bare_struct_parens {
+        Ok(Some(serializer.buffer()))
+    } else {
+        serializer.write(b"STRUCT(")?;
+        serializer.write_line_break().map(|_| None)
+    }
+}
+
+/// Type all tuple elements merge into, `None` if they don't or if it'd be a nested array.
+fn homogeneous_type(tuple_elements: &[(Type, Vec<u8>)]) -> Option<Type> {
+    tuple_elements
+        .iter()
+        .try_fold(Type::Any, |merged, (element_type, _)| {
+            merged.merge(element_type)
+        })
+        .filter(|merged| !matches!(merged, Type::Array(_)))
+}
+
+fn write_array<W: io::Write>(
+    serializer: &mut Serializer<W>,
+    tuple_elements: Vec<(Type, Vec<u8>)>,
+    element_type: Type,
+) -> Result<Type> {
+    serializer.write(b"[")?;
+    serializer.write_line_break()?;
+    for (i, (_, serialized)) in tuple_elements.into_iter().enumerate() {
+        if i > 0 {
+            serializer.write_separator()?;
+        }
+        serializer.write(&serialized)?;
+        serializer.recycle(serialized);
+    }
+    serializer.depth -= 1;
+    serializer.write_line_break()?;
+    serializer
+        .write(b"]")
+        .map(|_| Type::Array(Box::new(element_type)))
+}
+
+/// Replaces untyped `NULL` with `DEFAULT` in `VALUES` rows when `Config::missing_as_default`
+/// is set.
+fn row_value<'s, W>(
+    serializer: &Serializer<W>,
+    field_type: &Type,
+    serialized: &'s [u8],
+) -> &'s [u8] {
+    if serializer.config.missing_as_default && *field_type == Type::Any && serialized == b"NULL" {
+        b"DEFAULT"
+    } else {
+        serialized
+    }
+}
+
+fn write_field<W, T>(
+    serializer: &mut Serializer<W>,
+    first: bool,
+    key: Option<&str>,
+    value: &T,
+    expected_type: &Type,
+    annotate: bool,
+) -> Result<Type>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    if !first {
+        serializer.write_separator()?;
+    }
+    let field_type = serializer.serialize_field(key, value, expected_type)?;
+    if annotate {
+        write_field_name(serializer, key)?;
+    }
+    Ok(field_type)
+}
+
+fn write_serialized_field<W: io::Write>(
+    serializer: &mut Serializer<W>,
+    first: bool,
+    key: Option<&str>,
+    serialized: &[u8],
+) -> Result<()> {
+    if !first {
+        serializer.write_separator()?;
+    }
+    serializer.write(serialized)?;
+    write_field_name(serializer, key)
+}
+
+fn write_field_name<W: io::Write>(serializer: &mut Serializer<W>, key: Option<&str>) -> Result<()> {
+    if let Some(key) = key {
+        serializer.write(b" AS ")?;
+        serializer.write_identifier(key)?;
+    }
+    Ok(())
+}
+
 impl<'a, W: io::Write> ser::SerializeTuple for StructSerializer<'a, W> {
     type Ok = Type;
     type Error = Error;
@@ -137,6 +594,22 @@ impl<'a, W: io::Write> ser::SerializeTupleStruct for StructSerializer<'a, W> {
     }
 }
 
+impl<'a, W: io::Write> ser::SerializeTupleVariant for StructSerializer<'a, W> {
+    type Ok = Type;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.serialize_field(None, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.serialize_struct_end()
+    }
+}
+
 impl<'a, W: io::Write> ser::SerializeMap for StructSerializer<'a, W> {
     type Ok = Type;
     type Error = Error;
@@ -145,8 +618,12 @@ impl<'a, W: io::Write> ser::SerializeMap for StructSerializer<'a, W> {
     where
         T: ?Sized + Serialize,
     {
-        assert!(self.pending_key.is_none());
-        self.pending_key = Some(to_identifier(key)?);
+        if self.pending_key.is_some() {
+            return Err(Error::Message(
+                "serialize_key called twice without serialize_value".to_string(),
+            ));
+        }
+        self.pending_key = Some(to_key_identifier(key, &self.serializer.config)?);
         Ok(())
     }
 
@@ -154,20 +631,29 @@ impl<'a, W: io::Write> ser::SerializeMap for StructSerializer<'a, W> {
     where
         T: ?Sized + Serialize,
     {
-        let mut key = None;
-        std::mem::swap(&mut key, &mut self.pending_key);
-        self.serialize_field(key.as_deref(), value)
+        match self.pending_key.take() {
+            Some(key) => self.serialize_field(Some(&key), value),
+            None => Err(Error::Message(
+                "serialize_value called without serialize_key".to_string(),
+            )),
+        }
     }
 
-    fn serialize_entry<K: ?Sized, V: ?Sized>(&mut self, key: &K, value: &V) -> Result<()>
+    fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<()>
     where
-        K: Serialize,
-        V: Serialize,
+        K: ?Sized + Serialize,
+        V: ?Sized + Serialize,
     {
-        self.serialize_field(Some(&to_identifier(key)?), value)
+        let key = to_key_identifier(key, &self.serializer.config)?;
+        self.serialize_field(Some(&key), value)
     }
 
     fn end(self) -> Result<Self::Ok> {
+        if self.pending_key.is_some() {
+            return Err(Error::Message(
+                "serialize_key called without serialize_value".to_string(),
+            ));
+        }
         self.serialize_struct_end()
     }
 }
@@ -188,36 +674,82 @@ impl<'a, W: io::Write> ser::SerializeStruct for StructSerializer<'a, W> {
     }
 }
 
+impl<'a, W: io::Write> ser::SerializeStructVariant for StructSerializer<'a, W> {
+    type Ok = Type;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.serialize_field(Some(key), value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        self.serialize_struct_end()
+    }
+}
+
 enum FieldsBufferDecision {
     Buffered,
-    Expected,
+    /// Write the field now, checked against the carried type.
+    Expected(Type),
 }
 
 struct FieldsBuffer<'a> {
-    expected_fields: &'a [Field],
-    fields_buffer: HashMap<Field, Vec<u8>>,
+    expected_fields: Cow<'a, [Field]>,
+    /// Index of the next expected field.
+    next: usize,
+    /// Buffered fields keyed by name, their inferred type may differ from the expected one
+    /// (e.g. the expected type is `Any` because the field was `NULL` before).
+    fields_buffer: BufferedFields,
 }
 
+/// Fields serialized before their turn, keyed by name.
+pub(crate) type BufferedFields = HashMap<String, (Field, Vec<u8>)>;
+
 impl<'a> FieldsBuffer<'a> {
-    fn with_expected_fields(expected_fields: &'a [Field]) -> Self {
+    /// `fields_buffer` is an empty map, kept for reuse by `drain`.
+    fn with_expected_fields(
+        expected_fields: Cow<'a, [Field]>,
+        fields_buffer: BufferedFields,
+    ) -> Self {
         Self {
             expected_fields,
-            fields_buffer: HashMap::new(),
+            next: 0,
+            fields_buffer,
         }
     }
 
-    fn buffer<T>(&mut self, key: &str, value: &T) -> Result<()>
+    fn buffer<W, T>(
+        &mut self,
+        serializer: &mut Serializer<W>,
+        key: &str,
+        value: &T,
+        omit_unit: bool,
+    ) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        let mut serializer = Serializer { writer: Vec::new() };
-        let field_type = value.serialize(&mut serializer)?;
+        let expected_type = self
+            .expected_fields
+            .iter()
+            .find(|field| field.field_name.as_deref() == Some(key))
+            .map_or(Type::Any, |field| field.field_type.clone());
+        let mut buffer = serializer.buffer();
+        buffer.omit_unit = omit_unit;
+        let field_type = buffer.serialize_field(Some(key), value, &expected_type)?;
+        let unit_omitted = buffer.unit_omitted;
+        let serialized = serializer.absorb(buffer);
+        if unit_omitted {
+            // filled with `NULL` by `drain` like fields never serialized
+            serializer.recycle(serialized);
+            return Ok(());
+        }
+        let field = Field::with_type_and_name(field_type, Some(key.to_string()));
         if self
             .fields_buffer
-            .insert(
-                Field::with_type_and_name(field_type, Some(key.to_string())),
-                serializer.writer,
-            )
+            .insert(key.to_string(), (field, serialized))
             .is_some()
         {
             Err(Error::DuplicateStructField(key.to_string()))
@@ -226,23 +758,29 @@ impl<'a> FieldsBuffer<'a> {
         }
     }
 
-    fn decide<T>(&mut self, key: Option<&str>, value: &T) -> Result<FieldsBufferDecision>
+    fn decide<W, T>(
+        &mut self,
+        serializer: &mut Serializer<W>,
+        key: Option<&str>,
+        value: &T,
+        omit_unit: bool,
+    ) -> Result<FieldsBufferDecision>
     where
         T: ?Sized + Serialize,
     {
         // check if what we've got matches the first expected field
-        if let Some((head, tail)) = self.expected_fields.split_first() {
+        if let Some(head) = self.expected_fields.get(self.next) {
             match (head.field_name.as_ref(), key) {
                 (None, _) | (_, None) => {
-                    self.expected_fields = tail;
-                    Ok(FieldsBufferDecision::Expected)
+                    self.next += 1;
+                    Ok(FieldsBufferDecision::Expected(head.field_type.clone()))
                 }
                 (Some(expected_name), Some(name)) => {
                     if expected_name == name {
-                        self.expected_fields = tail;
-                        Ok(FieldsBufferDecision::Expected)
+                        self.next += 1;
+                        Ok(FieldsBufferDecision::Expected(head.field_type.clone()))
                     } else {
-                        self.buffer(name, value)
+                        self.buffer(serializer, name, value, omit_unit)
                             .map(|_| FieldsBufferDecision::Buffered)
                     }
                 }
@@ -254,17 +792,45 @@ impl<'a> FieldsBuffer<'a> {
         }
     }
 
-    fn drain(self) -> impl Iterator<Item = (&'a Field, Vec<u8>)> {
+    /// Accounts for an unnamed field written without going through `decide`, returns its
+    /// expected type.
+    fn expect_unnamed(&mut self) -> Result<Type> {
+        if let Some(field) = self.expected_fields.get(self.next) {
+            self.next += 1;
+            Ok(field.field_type.clone())
+        } else {
+            Err(Error::UnexpectedStructField(Field::with_name(None)))
+        }
+    }
+
+    /// Takes the buffered fields in the expected order, the ones that weren't serialized are
+    /// filled with `NULL`.
+    fn drain<W: io::Write>(self, serializer: &mut Serializer<W>) -> Result<Vec<(Field, Vec<u8>)>> {
         let Self {
             expected_fields,
+            next,
             mut fields_buffer,
         } = self;
-        expected_fields.iter().map(move |field| {
-            if let Some(serialized) = fields_buffer.remove(field) {
-                (field, serialized)
-            } else {
-                (field, b"NULL".to_vec())
-            }
-        })
+        let drained = expected_fields[next..]
+            .iter()
+            .map(|field| {
+                let buffered = field
+                    .field_name
+                    .as_ref()
+                    .and_then(|name| fields_buffer.remove(name));
+                match buffered {
+                    Some(buffered) => Ok(buffered),
+                    None => serializer
+                        .validate(&Type::Any)
+                        .map(|_| (field.clone(), b"NULL".to_vec())),
+                }
+            })
+            .collect::<Result<_>>()?;
+        // anything left wasn't expected at all
+        if let Some((_, (field, _))) = fields_buffer.drain().next() {
+            return Err(Error::UnexpectedStructField(field));
+        }
+        serializer.recycle_buffered_fields(fields_buffer);
+        Ok(drained)
     }
 }
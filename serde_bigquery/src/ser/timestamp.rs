@@ -0,0 +1,103 @@
+/// Checks if `s` is a `YYYY-MM-DD` date as written in a BigQuery `DATE` literal.
+pub(crate) fn is_date(s: &str) -> bool {
+    let s = s.as_bytes();
+    s.len() == 10 && number(&s[0..4]).is_some() && s[4] == b'-' && valid_month_day(&s[5..])
+}
+
+/// Checks if `s` is an RFC 3339 timestamp: `2024-01-02T03:04:05Z`, optionally with a
+/// fraction of a second and/or a `+HH:MM` offset, `T` can be replaced with a space.
+///
+/// Only exactly this shape is accepted (no dates without time, no missing time zone) to keep
+/// strings that just happen to look like dates as they are.
+pub(crate) fn is_timestamp(s: &str) -> bool {
+    let s = s.as_bytes();
+    if s.len() < 20 {
+        return false;
+    }
+    let in_range = |from, to, min, max| in_range(&s[from..to], min, max);
+    let valid_date_time = number(&s[0..4]).is_some()
+        && s[4] == b'-'
+        && valid_month_day(&s[5..10])
+        && (s[10] == b'T' || s[10] == b't' || s[10] == b' ')
+        && in_range(11, 13, 0, 23)
+        && s[13] == b':'
+        && in_range(14, 16, 0, 59)
+        && s[16] == b':'
+        && in_range(17, 19, 0, 60);
+    if !valid_date_time {
+        return false;
+    }
+
+    let mut rest = &s[19..];
+    if rest[0] == b'.' {
+        let digits = rest[1..].iter().take_while(|b| b.is_ascii_digit()).count();
+        if digits == 0 {
+            return false;
+        }
+        rest = &rest[1 + digits..];
+    }
+    match rest {
+        [b'Z'] | [b'z'] => true,
+        [b'+' | b'-', h1, h2, b':', m1, m2] => {
+            let hours = [*h1, *h2];
+            let minutes = [*m1, *m2];
+            hours.iter().chain(&minutes).all(u8::is_ascii_digit)
+                && (hours[0] - b'0') * 10 + (hours[1] - b'0') <= 23
+                && (minutes[0] - b'0') * 10 + (minutes[1] - b'0') <= 59
+        }
+        _ => false,
+    }
+}
+
+/// Checks `MM-DD`.
+fn valid_month_day(s: &[u8]) -> bool {
+    in_range(&s[0..2], 1, 12) && s[2] == b'-' && in_range(&s[3..5], 1, 31)
+}
+
+fn in_range(digits: &[u8], min: u32, max: u32) -> bool {
+    number(digits).is_some_and(|n| min <= n && n <= max)
+}
+
+fn number(digits: &[u8]) -> Option<u32> {
+    digits.iter().try_fold(0, |n, &b| {
+        if b.is_ascii_digit() {
+            Some(n * 10 + u32::from(b - b'0'))
+        } else {
+            None
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_timestamps() {
+        assert!(is_timestamp("2024-01-02T03:04:05Z"));
+        assert!(is_timestamp("2024-01-02 03:04:05.123456Z"));
+        assert!(is_timestamp("2024-01-02T03:04:05+05:30"));
+        assert!(is_timestamp("2024-01-02T03:04:05.5-08:00"));
+    }
+
+    #[test]
+    fn test_dates() {
+        assert!(is_date("2024-01-02"));
+        assert!(!is_date("2024-1-02"));
+        assert!(!is_date("2024-00-02"));
+        assert!(!is_date("2024-01-02T03:04:05Z"));
+        assert!(!is_date(""));
+    }
+
+    #[test]
+    fn test_not_timestamps() {
+        assert!(!is_timestamp(""));
+        assert!(!is_timestamp("2024-01-02"));
+        assert!(!is_timestamp("2024-01-02T03:04:05"));
+        assert!(!is_timestamp("2024-13-02T03:04:05Z"));
+        assert!(!is_timestamp("2024-01-02T24:04:05Z"));
+        assert!(!is_timestamp("2024-01-02T03:04:05.Z"));
+        assert!(!is_timestamp("2024-01-02T03:04:05Z and more"));
+        assert!(!is_timestamp("released 2024-01-02T03:04:05Z"));
+    }
+}
@@ -1,14 +1,20 @@
+use std::fmt::Display;
 use std::io;
 
 use serde::{ser, Serialize};
 
 use crate::error::{Error, Result};
-use crate::types::CheckType;
-use crate::{ser::serializer::Serializer, types::Type};
+use crate::types::{CheckType, Field};
+use crate::warning::Warning;
+use crate::{
+    ser::serializer::{Serializer, INACTIVE_VARIANT_TOKEN},
+    types::Type,
+};
 
 pub struct TypedSerializer<'a, W> {
     serializer: &'a mut Serializer<W>,
     expected_type: &'a Type,
+    coerce: bool,
 }
 
 impl<'a, W> TypedSerializer<'a, W> {
@@ -19,6 +25,66 @@ impl<'a, W> TypedSerializer<'a, W> {
         Self {
             serializer,
             expected_type,
+            coerce: false,
+        }
+    }
+
+    /// Writes scalars as strings when a `STRING` is expected instead of failing the type check.
+    pub(crate) fn with_coercion(self) -> Self {
+        Self {
+            coerce: true,
+            ..self
+        }
+    }
+
+    fn coerces_to_string(&self) -> bool {
+        self.coerce && matches!(self.expected_type, Type::String(_))
+    }
+
+    /// Fields of the struct holding a tuple or struct variant's fields, which is expected to be
+    /// the only field of the outer `STRUCT(... AS variant)`. `None` if they can be anything.
+    fn expected_variant_fields(&self) -> Result<Option<&'a [Field]>> {
+        match self.expected_type {
+            Type::Any => Ok(None),
+            Type::Struct(ref fields) if fields.len() == 1 => match fields[0].field_type {
+                Type::Any => Ok(None),
+                Type::Struct(ref fields) => Ok(Some(fields)),
+                _ => Err(Error::UnexpectedType {
+                    expected: self.expected_type.clone(),
+                    found: Type::Struct(vec![Field::with_type_and_name(
+                        Type::Struct(vec![]),
+                        None,
+                    )]),
+                }),
+            },
+            _ => Err(Error::UnexpectedType {
+                expected: self.expected_type.clone(),
+                found: Type::Struct(vec![]),
+            }),
+        }
+    }
+}
+
+impl<'a, W: io::Write> TypedSerializer<'a, W> {
+    fn coerce_to_string<T: Display>(&mut self, v: T) -> Result<Type> {
+        let path = self.serializer.current_path();
+        self.serializer
+            .warnings
+            .push(Warning::CoercedToString { path });
+        ser::Serializer::serialize_str(&mut *self.serializer, &v.to_string())
+    }
+
+    /// `NULL` of the expected type is allowed even with `Config::forbid_any`.
+    fn serialize_null(&mut self) -> Result<Type> {
+        match self.expected_type {
+            Type::Any => ser::Serializer::serialize_none(&mut *self.serializer),
+            expected_type if self.serializer.config.typed_null && !expected_type.contains_any() => {
+                self.serializer.validate(&Type::Any)?;
+                self.serializer
+                    .write_fmt(format_args!("CAST(NULL AS {})", expected_type))
+                    .map(|_| expected_type.clone())
+            }
+            _ => self.serializer.write_null(),
         }
     }
 }
@@ -36,73 +102,131 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut TypedSerializer<'a, W> {
     type SerializeStructVariant =
         <&'a mut Serializer<W> as ser::Serializer>::SerializeStructVariant;
 
+    fn is_human_readable(&self) -> bool {
+        !self.serializer.config.compact
+    }
+
     fn serialize_bool(self, v: bool) -> Result<Type> {
+        if self.coerces_to_string() {
+            return self.coerce_to_string(v);
+        }
         self.serializer
             .serialize_bool(v)
             .check_type(self.expected_type)
     }
 
     fn serialize_i8(self, v: i8) -> Result<Type> {
+        if self.coerces_to_string() {
+            return self.coerce_to_string(v);
+        }
         self.serializer
             .serialize_i8(v)
             .check_type(self.expected_type)
     }
 
     fn serialize_i16(self, v: i16) -> Result<Type> {
+        if self.coerces_to_string() {
+            return self.coerce_to_string(v);
+        }
         self.serializer
             .serialize_i16(v)
             .check_type(self.expected_type)
     }
 
     fn serialize_i32(self, v: i32) -> Result<Type> {
+        if self.coerces_to_string() {
+            return self.coerce_to_string(v);
+        }
         self.serializer
             .serialize_i32(v)
             .check_type(self.expected_type)
     }
 
     fn serialize_i64(self, v: i64) -> Result<Type> {
+        if self.coerces_to_string() {
+            return self.coerce_to_string(v);
+        }
         self.serializer
             .serialize_i64(v)
             .check_type(self.expected_type)
     }
 
     fn serialize_u8(self, v: u8) -> Result<Type> {
+        if self.coerces_to_string() {
+            return self.coerce_to_string(v);
+        }
         self.serializer
             .serialize_u8(v)
             .check_type(self.expected_type)
     }
 
     fn serialize_u16(self, v: u16) -> Result<Type> {
+        if self.coerces_to_string() {
+            return self.coerce_to_string(v);
+        }
         self.serializer
             .serialize_u16(v)
             .check_type(self.expected_type)
     }
 
     fn serialize_u32(self, v: u32) -> Result<Type> {
+        if self.coerces_to_string() {
+            return self.coerce_to_string(v);
+        }
         self.serializer
             .serialize_u32(v)
             .check_type(self.expected_type)
     }
 
     fn serialize_u64(self, v: u64) -> Result<Type> {
+        if self.coerces_to_string() {
+            return self.coerce_to_string(v);
+        }
         self.serializer
             .serialize_u64(v)
             .check_type(self.expected_type)
     }
 
+    fn serialize_i128(self, v: i128) -> Result<Type> {
+        if self.coerces_to_string() {
+            return self.coerce_to_string(v);
+        }
+        self.serializer
+            .serialize_i128(v)
+            .check_type(self.expected_type)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Type> {
+        if self.coerces_to_string() {
+            return self.coerce_to_string(v);
+        }
+        self.serializer
+            .serialize_u128(v)
+            .check_type(self.expected_type)
+    }
+
     fn serialize_f32(self, v: f32) -> Result<Type> {
+        if self.coerces_to_string() {
+            return self.coerce_to_string(v);
+        }
         self.serializer
             .serialize_f32(v)
             .check_type(self.expected_type)
     }
 
     fn serialize_f64(self, v: f64) -> Result<Type> {
+        if self.coerces_to_string() {
+            return self.coerce_to_string(v);
+        }
         self.serializer
             .serialize_f64(v)
             .check_type(self.expected_type)
     }
 
     fn serialize_char(self, v: char) -> Result<Type> {
+        if self.coerces_to_string() {
+            return self.coerce_to_string(v);
+        }
         self.serializer
             .serialize_char(v)
             .check_type(self.expected_type)
@@ -121,30 +245,27 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut TypedSerializer<'a, W> {
     }
 
     fn serialize_none(self) -> Result<Type> {
-        self.serializer
-            .serialize_none()
-            .check_type(self.expected_type)
+        self.serialize_null()
     }
 
     fn serialize_some<T>(self, value: &T) -> Result<Type>
     where
         T: ?Sized + Serialize,
     {
-        self.serializer
-            .serialize_some(value)
-            .check_type(self.expected_type)
+        // the expected type has to reach `NULL`s nested in `Some` (e.g. `Some(())`)
+        self.serializer.omit_unit = false;
+        value.serialize(self)
     }
 
     fn serialize_unit(self) -> Result<Type> {
-        self.serializer
-            .serialize_unit()
-            .check_type(self.expected_type)
+        if self.serializer.skip_unit() {
+            return Ok(Type::Any);
+        }
+        self.serialize_null()
     }
 
-    fn serialize_unit_struct(self, name: &'static str) -> Result<Type> {
-        self.serializer
-            .serialize_unit_struct(name)
-            .check_type(self.expected_type)
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Type> {
+        self.serialize_unit()
     }
 
     fn serialize_unit_variant(
@@ -162,6 +283,9 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut TypedSerializer<'a, W> {
     where
         T: ?Sized + Serialize,
     {
+        if name == INACTIVE_VARIANT_TOKEN && *self.expected_type != Type::Any {
+            return self.serialize_null();
+        }
         self.serializer
             .serialize_newtype_struct(name, value)
             .check_type(self.expected_type)
@@ -237,8 +361,13 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut TypedSerializer<'a, W> {
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
+        let expected_fields = self.expected_variant_fields()?;
         self.serializer
             .serialize_tuple_variant(name, variant_index, variant, len)
+            .map(move |ss| match expected_fields {
+                Some(fields) => ss.with_expected_fields(fields),
+                None => ss,
+            })
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
@@ -276,7 +405,12 @@ impl<'a, W: io::Write> ser::Serializer for &'a mut TypedSerializer<'a, W> {
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStructVariant> {
+        let expected_fields = self.expected_variant_fields()?;
         self.serializer
             .serialize_struct_variant(name, variant_index, variant, len)
+            .map(move |ss| match expected_fields {
+                Some(fields) => ss.with_expected_fields(fields),
+                None => ss,
+            })
     }
 }
@@ -77,9 +77,9 @@ impl ser::Serializer for UnsupportedSerializer {
         Err(Error::UnsupportedType)
     }
 
-    fn serialize_some<T: ?Sized>(self, _value: &T) -> Result<Self::Ok>
+    fn serialize_some<T>(self, _value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Error::UnsupportedType)
     }
@@ -101,18 +101,14 @@ impl ser::Serializer for UnsupportedSerializer {
         Err(Error::UnsupportedType)
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
-        self,
-        _name: &'static str,
-        _value: &T,
-    ) -> Result<Self::Ok>
+    fn serialize_newtype_struct<T>(self, _name: &'static str, _value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Error::UnsupportedType)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _variant_index: u32,
@@ -120,7 +116,7 @@ impl ser::Serializer for UnsupportedSerializer {
         _value: &T,
     ) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Error::UnsupportedType)
     }
@@ -174,9 +170,9 @@ impl ser::SerializeSeq for UnsupportedSerializer {
     type Ok = types::Type;
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+    fn serialize_element<T>(&mut self, _value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Error::UnsupportedType)
     }
@@ -190,9 +186,9 @@ impl ser::SerializeTuple for UnsupportedSerializer {
     type Ok = types::Type;
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+    fn serialize_element<T>(&mut self, _value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Error::UnsupportedType)
     }
@@ -206,9 +202,9 @@ impl ser::SerializeTupleStruct for UnsupportedSerializer {
     type Ok = types::Type;
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, _value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Error::UnsupportedType)
     }
@@ -222,9 +218,9 @@ impl ser::SerializeTupleVariant for UnsupportedSerializer {
     type Ok = types::Type;
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, _value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Error::UnsupportedType)
     }
@@ -238,16 +234,16 @@ impl ser::SerializeMap for UnsupportedSerializer {
     type Ok = types::Type;
     type Error = Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, _key: &T) -> Result<()>
+    fn serialize_key<T>(&mut self, _key: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Error::UnsupportedType)
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+    fn serialize_value<T>(&mut self, _value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Error::UnsupportedType)
     }
@@ -261,9 +257,9 @@ impl ser::SerializeStruct for UnsupportedSerializer {
     type Ok = types::Type;
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, _value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, _key: &'static str, _value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Error::UnsupportedType)
     }
@@ -277,9 +273,9 @@ impl ser::SerializeStructVariant for UnsupportedSerializer {
     type Ok = types::Type;
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, _value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, _key: &'static str, _value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
         Err(Error::UnsupportedType)
     }
@@ -3,10 +3,28 @@ use std::fmt::Write;
 use crate::error::{Error, Result};
 use crate::ser::identifier::format_as_identifier;
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+/// Fields compare and hash by type and name only, `nullable` is left out.
+#[derive(Clone, Debug)]
 pub struct Field {
     pub field_type: Type,
     pub field_name: Option<String>,
+    /// Set by `TypeInferer` on fields that were `NULL` or absent in some of the values.
+    pub(crate) nullable: bool,
+}
+
+impl PartialEq for Field {
+    fn eq(&self, other: &Self) -> bool {
+        self.field_type == other.field_type && self.field_name == other.field_name
+    }
+}
+
+impl Eq for Field {}
+
+impl std::hash::Hash for Field {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.field_type.hash(state);
+        self.field_name.hash(state);
+    }
 }
 
 impl Field {
@@ -14,6 +32,7 @@ impl Field {
         Self {
             field_type,
             field_name,
+            nullable: false,
         }
     }
 
@@ -21,6 +40,15 @@ impl Field {
         Self::with_type_and_name(Type::Any, field_name)
     }
 
+    pub fn with_nullable(self, nullable: bool) -> Self {
+        Self { nullable, ..self }
+    }
+
+    /// Whether the field was `NULL` or absent in some of the values seen by `TypeInferer`.
+    pub fn is_nullable(&self) -> bool {
+        self.nullable
+    }
+
     fn merge(&self, other: &Self) -> Option<Self> {
         self.field_type
             .merge(&other.field_type)
@@ -30,44 +58,259 @@ impl Field {
                     (Some(n), _) => Some(n.to_string()),
                     (_, n) => n.map(|s| s.to_string()),
                 },
+                nullable: self.nullable || other.nullable,
             })
     }
 }
 
-impl std::fmt::Display for Field {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl Field {
+    fn write_ddl(&self, f: &mut std::fmt::Formatter<'_>, dialect: Dialect) -> std::fmt::Result {
         if let Some(ref field_name) = self.field_name {
             f.write_str(&format_as_identifier(field_name))?;
             f.write_char(' ')?
         }
-        f.write_fmt(format_args!("{}", self.field_type))
+        self.field_type.write_ddl(f, dialect)
     }
 }
 
+impl std::fmt::Display for Field {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.write_ddl(f, Dialect::Standard)
+    }
+}
+
+/// Set of type names used when rendering types.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Dialect {
+    /// Standard SQL names: `INT64`, `FLOAT64`, `BOOL`, `STRUCT`.
+    #[default]
+    Standard,
+    /// Legacy SQL names: `INTEGER`, `FLOAT`, `BOOLEAN`, `RECORD`.
+    Legacy,
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Type {
     Any,
     Bool,
-    Number,
-    String,
-    Bytes,
+    Int64,
+    Float64,
+    /// `NUMERIC` with optional `(precision, scale)` parameters.
+    Numeric(Option<(u8, u8)>),
+    BigNumeric,
+    /// `STRING` with optional maximum length.
+    String(Option<u64>),
+    /// `BYTES` with optional maximum length.
+    Bytes(Option<u64>),
+    Json,
+    Timestamp,
+    Date,
+    Datetime,
+    Geography,
+    Interval,
     Struct(Vec<Field>),
     Array(Box<Type>),
 }
 
+/// Single difference between two types found by `Type::diff`.
+///
+/// Paths are dotted field names (`"outer.inner"`), unnamed fields are named by their
+/// position and array elements share the path of the array.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TypeDiff {
+    AddedField { path: String, field_type: Type },
+    RemovedField { path: String, field_type: Type },
+    ChangedType { path: String, from: Type, to: Type },
+}
+
 impl Type {
     pub fn any_array() -> Self {
         Self::Array(Box::new(Self::Any))
     }
 
+    /// Lists the differences going from `self` to `other`, e.g. from a table's declared type
+    /// to the type inferred from new data. Struct fields are matched by name.
+    pub fn diff(&self, other: &Self) -> Vec<TypeDiff> {
+        let mut diffs = Vec::new();
+        self.diff_at("", other, &mut diffs);
+        diffs
+    }
+
+    fn diff_at(&self, path: &str, other: &Self, diffs: &mut Vec<TypeDiff>) {
+        match (self, other) {
+            (Self::Struct(fields), Self::Struct(other_fields)) => {
+                let named = |fields: &[Field]| -> Vec<(String, Type)> {
+                    fields
+                        .iter()
+                        .enumerate()
+                        .map(|(i, f)| {
+                            let name = f.field_name.clone().unwrap_or_else(|| i.to_string());
+                            let path = if path.is_empty() {
+                                name
+                            } else {
+                                format!("{}.{}", path, name)
+                            };
+                            (path, f.field_type.clone())
+                        })
+                        .collect()
+                };
+                let fields = named(fields);
+                let other_fields = named(other_fields);
+                for (field_path, field_type) in &fields {
+                    match other_fields.iter().find(|(p, _)| p == field_path) {
+                        Some((_, other_type)) => field_type.diff_at(field_path, other_type, diffs),
+                        None => diffs.push(TypeDiff::RemovedField {
+                            path: field_path.clone(),
+                            field_type: field_type.clone(),
+                        }),
+                    }
+                }
+                for (field_path, field_type) in other_fields {
+                    if !fields.iter().any(|(p, _)| *p == field_path) {
+                        diffs.push(TypeDiff::AddedField {
+                            path: field_path,
+                            field_type,
+                        });
+                    }
+                }
+            }
+            (Self::Array(element_type), Self::Array(other_element_type)) => {
+                element_type.diff_at(path, other_element_type, diffs)
+            }
+            _ if self != other => diffs.push(TypeDiff::ChangedType {
+                path: path.to_string(),
+                from: self.clone(),
+                to: other.clone(),
+            }),
+            _ => {}
+        }
+    }
+
+    /// Position in the numeric tower `INT64 ⊂ NUMERIC ⊂ BIGNUMERIC ⊂ FLOAT64`, following
+    /// BigQuery's implicit coercions.
+    fn numeric_rank(&self) -> Option<u8> {
+        match self {
+            Self::Int64 => Some(0),
+            Self::Numeric(_) => Some(1),
+            Self::BigNumeric => Some(2),
+            Self::Float64 => Some(3),
+            _ => None,
+        }
+    }
+
+    /// Renders the type as used in a column definition, e.g. `STRUCT<`a` INT64, `b` ARRAY<STRING>>`.
+    ///
+    /// Fails with `Error::UnresolvedType` if any part of the type couldn't be inferred as
+    /// there's no SQL type for it.
+    pub fn to_ddl(&self) -> Result<String> {
+        self.to_ddl_with_dialect(Dialect::Standard)
+    }
+
+    /// Like `to_ddl` but with type names of the given dialect.
+    ///
+    /// Legacy SQL has no syntax for `RECORD` and repeated types, those fail with
+    /// `Error::Message` (`to_json_schema_with_dialect` describes them).
+    pub fn to_ddl_with_dialect(&self, dialect: Dialect) -> Result<String> {
+        struct Ddl<'a>(&'a Type, Dialect);
+
+        impl std::fmt::Display for Ddl<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0.write_ddl(f, self.1)
+            }
+        }
+
+        if self.contains_any() {
+            Err(Error::UnresolvedType(self.clone()))
+        } else if dialect == Dialect::Legacy && matches!(self, Self::Struct(_) | Self::Array(_)) {
+            Err(Error::Message(format!(
+                "no legacy SQL type for {}, use the JSON schema",
+                self
+            )))
+        } else {
+            Ok(Ddl(self, dialect).to_string())
+        }
+    }
+
+    /// Name of the type without parameters (`NUMERIC`, `STRUCT`, `ARRAY`).
+    pub(crate) fn name(&self, dialect: Dialect) -> &'static str {
+        let legacy = dialect == Dialect::Legacy;
+        match self {
+            Self::Any => "?",
+            Self::Bool if legacy => "BOOLEAN",
+            Self::Bool => "BOOL",
+            Self::Int64 if legacy => "INTEGER",
+            Self::Int64 => "INT64",
+            Self::Float64 if legacy => "FLOAT",
+            Self::Float64 => "FLOAT64",
+            Self::Numeric(_) => "NUMERIC",
+            Self::BigNumeric => "BIGNUMERIC",
+            Self::String(_) => "STRING",
+            Self::Bytes(_) => "BYTES",
+            Self::Json => "JSON",
+            Self::Timestamp => "TIMESTAMP",
+            Self::Date => "DATE",
+            Self::Datetime => "DATETIME",
+            Self::Geography => "GEOGRAPHY",
+            Self::Interval => "INTERVAL",
+            Self::Struct(_) if legacy => "RECORD",
+            Self::Struct(_) => "STRUCT",
+            Self::Array(_) => "ARRAY",
+        }
+    }
+
+    fn write_ddl(&self, f: &mut std::fmt::Formatter<'_>, dialect: Dialect) -> std::fmt::Result {
+        f.write_str(self.name(dialect))?;
+        match self {
+            Type::Numeric(Some((precision, scale))) => write!(f, "({}, {})", precision, scale),
+            Type::String(Some(length)) | Type::Bytes(Some(length)) => write!(f, "({})", length),
+            Type::Struct(fields) => {
+                f.write_char('<')?;
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    field.write_ddl(f, dialect)?;
+                }
+                f.write_char('>')
+            }
+            Type::Array(t) => {
+                f.write_char('<')?;
+                t.write_ddl(f, dialect)?;
+                f.write_char('>')
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks if there's any part of the type that couldn't be inferred.
+    pub fn contains_any(&self) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Struct(fields) => fields.iter().any(|f| f.field_type.contains_any()),
+            Self::Array(t) => t.contains_any(),
+            _ => false,
+        }
+    }
+
     pub fn matches(&self, other: &Self) -> bool {
         match (self, other) {
             (Self::Any, _) => true,
             (_, Self::Any) => true,
             (Self::Bool, Self::Bool) => true,
-            (Self::Number, Self::Number) => true,
-            (Self::String, Self::String) => true,
-            (Self::Bytes, Self::Bytes) => true,
+            (Self::Numeric(params), Self::Numeric(other_params)) => {
+                params.is_none() || other_params.is_none() || params == other_params
+            }
+            _ if self.numeric_rank().is_some() && other.numeric_rank().is_some() => true,
+            (Self::String(length), Self::String(other_length))
+            | (Self::Bytes(length), Self::Bytes(other_length)) => {
+                length.is_none() || other_length.is_none() || length == other_length
+            }
+            (Self::Json, Self::Json) => true,
+            (Self::Timestamp, Self::Timestamp) => true,
+            (Self::Date, Self::Date) => true,
+            (Self::Datetime, Self::Datetime) => true,
+            (Self::Geography, Self::Geography) => true,
+            (Self::Interval, Self::Interval) => true,
             (Self::Struct(fields), Self::Struct(other_fields)) => {
                 fields.len() == other_fields.len()
                     && fields
@@ -80,14 +323,149 @@ impl Type {
         }
     }
 
+    /// Checks that `candidate` (e.g. the type returned by serializing a value) matches this
+    /// expected type, failing with `Error::UnexpectedType` if it doesn't.
+    pub fn validate(&self, candidate: &Self) -> Result<()> {
+        if self.matches(candidate) {
+            Ok(())
+        } else {
+            Err(Error::UnexpectedType {
+                expected: self.clone(),
+                found: candidate.clone(),
+            })
+        }
+    }
+
+    /// Hash of the shape of the type: field names (and `nullable`) are ignored, so structs
+    /// only differing in names hash the same while field order still matters.
+    ///
+    /// Computed with FNV-1a so it's stable across runs and Rust versions, unlike the derived
+    /// `Hash` used with `DefaultHasher`.
+    pub fn structural_hash(&self) -> u64 {
+        let mut hash = 0xcbf2_9ce4_8422_2325;
+        self.hash_structure(&mut |byte| {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        });
+        hash
+    }
+
+    fn hash_structure(&self, write: &mut impl FnMut(u8)) {
+        match self {
+            Self::Any => write(0),
+            Self::Bool => write(1),
+            Self::Int64 => write(2),
+            Self::Float64 => write(3),
+            Self::Numeric(None) => write(4),
+            Self::Numeric(Some((precision, scale))) => {
+                write(5);
+                write(*precision);
+                write(*scale);
+            }
+            Self::String(None) => write(6),
+            Self::Bytes(None) => write(7),
+            Self::String(Some(length)) => {
+                write(15);
+                for byte in length.to_le_bytes() {
+                    write(byte);
+                }
+            }
+            Self::Bytes(Some(length)) => {
+                write(16);
+                for byte in length.to_le_bytes() {
+                    write(byte);
+                }
+            }
+            Self::Json => write(8),
+            Self::BigNumeric => write(12),
+            Self::Timestamp => write(9),
+            Self::Date => write(13),
+            Self::Datetime => write(14),
+            Self::Geography => write(17),
+            Self::Interval => write(18),
+            Self::Struct(fields) => {
+                write(10);
+                for byte in (fields.len() as u64).to_le_bytes() {
+                    write(byte);
+                }
+                for field in fields {
+                    field.field_type.hash_structure(write);
+                }
+            }
+            Self::Array(element_type) => {
+                write(11);
+                element_type.hash_structure(write);
+            }
+        }
+    }
+
+    /// Checks if a value of this type can be stored in a `target` column, following BigQuery's
+    /// implicit coercions (unlike `matches` this isn't symmetric).
+    ///
+    /// Numeric types are only assignable upwards along `INT64 ⊂ NUMERIC ⊂ BIGNUMERIC ⊂ FLOAT64`.
+    pub fn is_assignable_to(&self, target: &Self) -> bool {
+        match (self, target) {
+            (Self::Any, _) | (_, Self::Any) => true,
+            (Self::Numeric(params), Self::Numeric(target_params)) => {
+                target_params.is_none() || params == target_params
+            }
+            (Self::String(length), Self::String(target_length))
+            | (Self::Bytes(length), Self::Bytes(target_length)) => match (length, target_length) {
+                (_, None) => true,
+                (Some(length), Some(target_length)) => length <= target_length,
+                (None, Some(_)) => false,
+            },
+            _ if self.numeric_rank().is_some() && target.numeric_rank().is_some() => {
+                self.numeric_rank() <= target.numeric_rank()
+            }
+            (Self::Struct(fields), Self::Struct(target_fields)) => {
+                fields.len() == target_fields.len()
+                    && fields
+                        .iter()
+                        .zip(target_fields)
+                        .all(|(f1, f2)| f1.field_type.is_assignable_to(&f2.field_type))
+            }
+            (Self::Array(element_type), Self::Array(target_element_type)) => {
+                element_type.is_assignable_to(target_element_type)
+            }
+            _ => self == target,
+        }
+    }
+
+    /// Finds a type both `self` and `other` can be represented as.
+    ///
+    /// Numeric types are widened along `INT64 ⊂ NUMERIC ⊂ BIGNUMERIC ⊂ FLOAT64`, so an array
+    /// mixing integers and floats becomes `ARRAY<FLOAT64>`.
     pub fn merge(&self, other: &Self) -> Option<Self> {
         match (self, other) {
             (Self::Any, _) => Some(other.clone()),
             (_, Self::Any) => Some(self.clone()),
             (Self::Bool, Self::Bool) => Some(Self::Bool),
-            (Self::Number, Self::Number) => Some(Self::Number),
-            (Self::String, Self::String) => Some(Self::String),
-            (Self::Bytes, Self::Bytes) => Some(Self::Bytes),
+            (Self::Numeric(params), Self::Numeric(other_params)) => match (params, other_params) {
+                (Some(p1), Some(p2)) if p1 != p2 => None,
+                (Some(p), _) | (_, Some(p)) => Some(Self::Numeric(Some(*p))),
+                (None, None) => Some(Self::Numeric(None)),
+            },
+            _ if self.numeric_rank().is_some() && other.numeric_rank().is_some() => {
+                if self.numeric_rank() >= other.numeric_rank() {
+                    Some(self.clone())
+                } else {
+                    Some(other.clone())
+                }
+            }
+            // the longer maximum length fits values of both, no maximum fits anything
+            (Self::String(length), Self::String(other_length)) => {
+                Some(Self::String(max_length(*length, *other_length)))
+            }
+            (Self::Bytes(length), Self::Bytes(other_length)) => {
+                Some(Self::Bytes(max_length(*length, *other_length)))
+            }
+            (Self::Json, Self::Json) => Some(Self::Json),
+            (Self::Timestamp, Self::Timestamp) => Some(Self::Timestamp),
+            (Self::Date, Self::Date) => Some(Self::Date),
+            (Self::Datetime, Self::Datetime) => Some(Self::Datetime),
+            (Self::Geography, Self::Geography) => Some(Self::Geography),
+            (Self::Interval, Self::Interval) => Some(Self::Interval),
             (Self::Struct(fields), Self::Struct(other_fields)) => {
                 if fields.len() == other_fields.len() {
                     fields
@@ -108,29 +486,14 @@ impl Type {
     }
 }
 
+/// Longer of two `STRING`/`BYTES` maximum lengths, `None` (unbounded) if either is.
+fn max_length(length: Option<u64>, other_length: Option<u64>) -> Option<u64> {
+    length.zip(other_length).map(|(a, b)| a.max(b))
+}
+
 impl std::fmt::Display for Type {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Type::Any => f.write_char('?'),
-            Type::Bool => f.write_str("BOOL"),
-            Type::Number => f.write_str("DOUBLE"), // it can also be any numerical type but let's assume it's DOUBLE
-            Type::String => f.write_str("STRING"),
-            Type::Bytes => f.write_str("BYTES"),
-            Type::Struct(fields) => {
-                let mut first_field = true;
-                f.write_str("STRUCT<")?;
-                for field in fields {
-                    if first_field {
-                        first_field = false;
-                    } else {
-                        f.write_str(", ")?;
-                    }
-                    f.write_fmt(format_args!("{}", field))?;
-                }
-                f.write_str(">")
-            }
-            Type::Array(t) => write!(f, "ARRAY<{}>", t),
-        }
+        self.write_ddl(f, Dialect::Standard)
     }
 }
 
@@ -163,31 +526,371 @@ mod test {
     #[test]
     fn test_matches_any() {
         assert!(Type::Any.matches(&Type::Bool));
-        assert!(Type::Any.matches(&Type::Number));
-        assert!(Type::Any.matches(&Type::String));
-        assert!(Type::Any.matches(&Type::Bytes));
+        assert!(Type::Any.matches(&Type::Int64));
+        assert!(Type::Any.matches(&Type::Float64));
+        assert!(Type::Any.matches(&Type::String(None)));
+        assert!(Type::Any.matches(&Type::Bytes(None)));
         assert!(Type::Any.matches(&Type::Struct(vec![])));
         assert!(Type::Any.matches(&Type::Array(Box::new(Type::Any))));
 
         assert!(Type::Bool.matches(&Type::Any));
-        assert!(Type::Number.matches(&Type::Any));
-        assert!(Type::String.matches(&Type::Any));
-        assert!(Type::Bytes.matches(&Type::Any));
+        assert!(Type::Int64.matches(&Type::Any));
+        assert!(Type::Float64.matches(&Type::Any));
+        assert!(Type::String(None).matches(&Type::Any));
+        assert!(Type::Bytes(None).matches(&Type::Any));
         assert!(Type::Struct(vec![]).matches(&Type::Any));
         assert!(Type::Array(Box::new(Type::Any)).matches(&Type::Any));
     }
 
+    #[test]
+    fn test_display_numeric() {
+        assert_eq!(Type::Numeric(None).to_string(), "NUMERIC");
+        assert_eq!(Type::Numeric(Some((10, 2))).to_string(), "NUMERIC(10, 2)");
+        assert_eq!(
+            Type::Struct(vec![Field::with_type_and_name(
+                Type::Numeric(Some((38, 9))),
+                Some("price".to_string())
+            )])
+            .to_string(),
+            "STRUCT<`price` NUMERIC(38, 9)>"
+        );
+    }
+
+    #[test]
+    fn test_parameterized_lengths() {
+        assert_eq!(Type::String(None).to_string(), "STRING");
+        assert_eq!(Type::Bytes(None).to_string(), "BYTES");
+        assert_eq!(Type::String(Some(10)).to_string(), "STRING(10)");
+        assert_eq!(
+            Type::Struct(vec![Field::with_type_and_name(
+                Type::Array(Box::new(Type::Bytes(Some(100)))),
+                Some("keys".to_string())
+            )])
+            .to_ddl()
+            .unwrap(),
+            "STRUCT<`keys` ARRAY<BYTES(100)>>"
+        );
+
+        assert!(Type::String(Some(10)).matches(&Type::String(None)));
+        assert!(!Type::String(Some(10)).matches(&Type::String(Some(20))));
+        assert!(!Type::String(Some(10)).matches(&Type::Bytes(Some(10))));
+        assert_eq!(
+            Type::String(None).merge(&Type::String(Some(10))),
+            Some(Type::String(None))
+        );
+        assert_eq!(
+            Type::Bytes(Some(10)).merge(&Type::Bytes(None)),
+            Some(Type::Bytes(None))
+        );
+        assert_eq!(
+            Type::Bytes(Some(20)).merge(&Type::Bytes(Some(10))),
+            Some(Type::Bytes(Some(20)))
+        );
+        assert!(Type::String(Some(10)).is_assignable_to(&Type::String(Some(20))));
+        assert!(Type::String(Some(10)).is_assignable_to(&Type::String(None)));
+        assert!(!Type::String(Some(20)).is_assignable_to(&Type::String(Some(10))));
+        assert!(!Type::String(None).is_assignable_to(&Type::String(Some(10))));
+        assert_ne!(
+            Type::String(Some(10)).structural_hash(),
+            Type::Bytes(Some(10)).structural_hash()
+        );
+    }
+
+    #[test]
+    fn test_merge_numeric() {
+        assert_eq!(
+            Type::Numeric(None).merge(&Type::Numeric(Some((10, 2)))),
+            Some(Type::Numeric(Some((10, 2))))
+        );
+        assert_eq!(
+            Type::Numeric(Some((10, 2))).merge(&Type::Numeric(Some((12, 2)))),
+            None
+        );
+    }
+
+    #[test]
+    fn test_merge_numeric_tower() {
+        assert_eq!(Type::Int64.merge(&Type::Float64), Some(Type::Float64));
+        assert_eq!(Type::Float64.merge(&Type::Int64), Some(Type::Float64));
+        assert_eq!(
+            Type::Int64.merge(&Type::Numeric(None)),
+            Some(Type::Numeric(None))
+        );
+        assert_eq!(
+            Type::Numeric(Some((10, 2))).merge(&Type::Float64),
+            Some(Type::Float64)
+        );
+        assert_eq!(Type::Int64.merge(&Type::String(None)), None);
+    }
+
+    #[test]
+    fn test_contains_any() {
+        assert!(Type::Any.contains_any());
+        assert!(Type::any_array().contains_any());
+        assert!(Type::Struct(vec![Field::with_name(Some("a".to_string()))]).contains_any());
+        assert!(!Type::Array(Box::new(Type::Int64)).contains_any());
+        assert!(!Type::Struct(vec![]).contains_any());
+    }
+
     #[test]
     fn test_matches_same() {
         for t in [
             Type::Bool,
-            Type::Number,
-            Type::String,
-            Type::Bytes,
+            Type::Int64,
+            Type::Float64,
+            Type::Numeric(None),
+            Type::Numeric(Some((10, 2))),
+            Type::String(None),
+            Type::Bytes(None),
             Type::Struct(vec![]),
             Type::Array(Box::new(Type::Bool)),
         ] {
             assert!(t.matches(&t));
         }
     }
+
+    #[test]
+    fn test_assignable_numeric_tower() {
+        assert!(Type::Int64.is_assignable_to(&Type::Int64));
+        assert!(Type::Int64.is_assignable_to(&Type::Numeric(None)));
+        assert!(Type::Int64.is_assignable_to(&Type::Float64));
+        assert!(Type::Numeric(None).is_assignable_to(&Type::Float64));
+        assert!(!Type::Float64.is_assignable_to(&Type::Int64));
+        assert!(!Type::Float64.is_assignable_to(&Type::Numeric(None)));
+        assert!(!Type::Numeric(None).is_assignable_to(&Type::Int64));
+        assert!(Type::Numeric(Some((10, 2))).is_assignable_to(&Type::Numeric(None)));
+        assert!(!Type::Numeric(None).is_assignable_to(&Type::Numeric(Some((10, 2)))));
+        assert!(!Type::Int64.is_assignable_to(&Type::String(None)));
+    }
+
+    #[test]
+    fn test_assignable_struct() {
+        let field = |name: &str, t| Field::with_type_and_name(t, Some(name.to_string()));
+        let value = Type::Struct(vec![
+            field("a", Type::Int64),
+            field("b", Type::Array(Box::new(Type::Int64))),
+        ]);
+        let target = Type::Struct(vec![
+            field("a", Type::Float64),
+            field("b", Type::Array(Box::new(Type::Numeric(None)))),
+        ]);
+        assert!(value.is_assignable_to(&target));
+        assert!(!target.is_assignable_to(&value));
+        assert!(!value.is_assignable_to(&Type::Struct(vec![field("a", Type::Float64)])));
+    }
+
+    #[test]
+    fn test_validate() {
+        let field = |name: &str, t| Field::with_type_and_name(t, Some(name.to_string()));
+        let expected = Type::Struct(vec![
+            field("id", Type::Int64),
+            field("tags", Type::Array(Box::new(Type::String(None)))),
+        ]);
+
+        let (_, inferred) = crate::to_string_with_type(&(7, vec!["a"])).unwrap();
+        expected.validate(&inferred).unwrap();
+        // `NULL` and an empty array fit too
+        let (_, inferred) =
+            crate::to_string_with_type(&(None::<i64>, Vec::<String>::new())).unwrap();
+        expected.validate(&inferred).unwrap();
+
+        let (_, inferred) = crate::to_string_with_type(&(7, vec![1])).unwrap();
+        assert!(matches!(
+            expected.validate(&inferred),
+            Err(Error::UnexpectedType { expected: e, found }) if e == expected && found == inferred
+        ));
+        let missing_field = Type::Struct(vec![field("id", Type::Int64)]);
+        assert!(matches!(
+            expected.validate(&missing_field),
+            Err(Error::UnexpectedType { .. })
+        ));
+    }
+
+    #[test]
+    fn test_structural_hash() {
+        let field = |name: &str, t| Field::with_type_and_name(t, Some(name.to_string()));
+        let t1 = Type::Struct(vec![
+            field("a", Type::Int64),
+            field("b", Type::String(None)),
+        ]);
+        let t2 = Type::Struct(vec![
+            field("x", Type::Int64),
+            field("y", Type::String(None)),
+        ]);
+        let t3 = Type::Struct(vec![
+            field("a", Type::String(None)),
+            field("b", Type::Int64),
+        ]);
+        assert_eq!(t1.structural_hash(), t2.structural_hash());
+        assert_ne!(t1.structural_hash(), t3.structural_hash());
+        assert_ne!(
+            Type::Array(Box::new(t1.clone())).structural_hash(),
+            t1.structural_hash()
+        );
+        assert_ne!(
+            Type::Numeric(None).structural_hash(),
+            Type::Numeric(Some((10, 2))).structural_hash()
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let field =
+            |name: &str, field_type| Field::with_type_and_name(field_type, Some(name.to_string()));
+        let declared = Type::Struct(vec![
+            field("id", Type::Int64),
+            field("name", Type::String(None)),
+            field(
+                "address",
+                Type::Struct(vec![
+                    field("city", Type::String(None)),
+                    field("zip", Type::Int64),
+                ]),
+            ),
+        ]);
+        let inferred = Type::Struct(vec![
+            field("id", Type::Int64),
+            field(
+                "address",
+                Type::Struct(vec![
+                    field("city", Type::String(None)),
+                    field("zip", Type::String(None)),
+                    field("street", Type::String(None)),
+                ]),
+            ),
+            field("tags", Type::Array(Box::new(Type::String(None)))),
+        ]);
+        assert_eq!(
+            declared.diff(&inferred),
+            vec![
+                TypeDiff::RemovedField {
+                    path: "name".to_string(),
+                    field_type: Type::String(None)
+                },
+                TypeDiff::ChangedType {
+                    path: "address.zip".to_string(),
+                    from: Type::Int64,
+                    to: Type::String(None)
+                },
+                TypeDiff::AddedField {
+                    path: "address.street".to_string(),
+                    field_type: Type::String(None)
+                },
+                TypeDiff::AddedField {
+                    path: "tags".to_string(),
+                    field_type: Type::Array(Box::new(Type::String(None)))
+                },
+            ]
+        );
+        assert!(declared.diff(&declared).is_empty());
+        assert_eq!(
+            Type::Array(Box::new(Type::Int64)).diff(&Type::Array(Box::new(Type::Float64))),
+            vec![TypeDiff::ChangedType {
+                path: String::new(),
+                from: Type::Int64,
+                to: Type::Float64
+            }]
+        );
+    }
+
+    #[test]
+    fn test_to_ddl() {
+        let field =
+            |name: &str, field_type| Field::with_type_and_name(field_type, Some(name.to_string()));
+        let t = Type::Struct(vec![
+            field("a", Type::Int64),
+            field("b", Type::Array(Box::new(Type::String(None)))),
+            field(
+                "c",
+                Type::Array(Box::new(Type::Struct(vec![field("d", Type::Date)]))),
+            ),
+        ]);
+        assert_eq!(
+            t.to_ddl().unwrap(),
+            "STRUCT<`a` INT64, `b` ARRAY<STRING>, `c` ARRAY<STRUCT<`d` DATE>>>"
+        );
+        assert!(matches!(
+            Type::Any.to_ddl(),
+            Err(Error::UnresolvedType(Type::Any))
+        ));
+        assert!(Type::Struct(vec![field("a", Type::any_array())])
+            .to_ddl()
+            .is_err());
+    }
+
+    #[test]
+    fn test_to_ddl_nested_records() {
+        use serde_derive::Serialize;
+
+        #[derive(Serialize)]
+        struct Inner {
+            c: Option<&'static str>,
+        }
+
+        #[derive(Serialize)]
+        struct Row {
+            a: i64,
+            b: Vec<Inner>,
+        }
+
+        let row = Row {
+            a: 1,
+            b: vec![Inner { c: Some("x") }],
+        };
+        let (_, row_type) = crate::to_string_with_type(&row).unwrap();
+        assert_eq!(
+            row_type.to_ddl().unwrap(),
+            "STRUCT<`a` INT64, `b` ARRAY<STRUCT<`c` STRING>>>"
+        );
+
+        // a field nested two levels deep that's only ever `NULL` has no type
+        let row = Row {
+            a: 1,
+            b: vec![Inner { c: None }],
+        };
+        let (_, row_type) = crate::to_string_with_type(&row).unwrap();
+        assert!(matches!(row_type.to_ddl(), Err(Error::UnresolvedType(_))));
+    }
+
+    #[test]
+    fn test_dialects() {
+        let field =
+            |name: &str, field_type| Field::with_type_and_name(field_type, Some(name.to_string()));
+        let t = Type::Struct(vec![
+            field("id", Type::Int64),
+            field(
+                "inner",
+                Type::Struct(vec![
+                    field("ok", Type::Bool),
+                    field("scores", Type::Array(Box::new(Type::Float64))),
+                    field("price", Type::Numeric(Some((10, 2)))),
+                ]),
+            ),
+        ]);
+        assert_eq!(
+            t.to_ddl_with_dialect(Dialect::Standard).unwrap(),
+            "STRUCT<`id` INT64, `inner` STRUCT<`ok` BOOL, `scores` ARRAY<FLOAT64>, `price` NUMERIC(10, 2)>>"
+        );
+        assert!(matches!(
+            t.to_ddl_with_dialect(Dialect::Legacy),
+            Err(Error::Message(_))
+        ));
+        assert!(Type::Array(Box::new(Type::Int64))
+            .to_ddl_with_dialect(Dialect::Legacy)
+            .is_err());
+        assert_eq!(
+            Type::Int64.to_ddl_with_dialect(Dialect::Legacy).unwrap(),
+            "INTEGER"
+        );
+        assert_eq!(
+            Type::Float64.to_ddl_with_dialect(Dialect::Legacy).unwrap(),
+            "FLOAT"
+        );
+        assert_eq!(
+            Type::Numeric(Some((10, 2)))
+                .to_ddl_with_dialect(Dialect::Legacy)
+                .unwrap(),
+            "NUMERIC(10, 2)"
+        );
+    }
 }
@@ -0,0 +1,88 @@
+use sqlparser::dialect::BigQueryDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::Token;
+
+use crate::error::{Error, Result};
+
+/// Checks that `literal` parses as a single BigQuery expression, e.g. to catch malformed
+/// `Serializer` output in tests (see also `Config::validate_output`).
+///
+/// Only the syntax is checked, types aren't (`STRUCT(1) = "a"` passes).
+pub fn validate_literal(literal: &str) -> Result<()> {
+    Parser::new(&BigQueryDialect)
+        .try_with_sql(literal)
+        .and_then(|mut parser| {
+            parser.parse_expr()?;
+            parser.expect_token(&Token::EOF).map(|_| ())
+        })
+        .map_err(|e| Error::InvalidOutput(e.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ser::{to_string, to_string_with_config, Config};
+    use crate::wrappers::{Date, Interval, Json};
+    use serde_derive::Serialize;
+
+    #[derive(Serialize)]
+    struct Inner {
+        name: &'static str,
+        #[serde(with = "serde_bytes")]
+        key: Vec<u8>,
+        at: Date<'static>,
+    }
+
+    #[derive(Serialize)]
+    struct Outer {
+        id: u64,
+        score: f64,
+        inner: Vec<Inner>,
+        pair: (i32, Option<bool>),
+        payload: Json<'static>,
+        elapsed: Interval<'static>,
+    }
+
+    fn outer() -> Outer {
+        Outer {
+            id: 1,
+            score: f64::NAN,
+            inner: vec![Inner {
+                name: "it's \"quoted\"",
+                key: vec![0, 255],
+                at: Date::new("2024-01-02"),
+            }],
+            pair: (-1, None),
+            payload: Json::new(r#"{"a":[1]}"#),
+            elapsed: Interval::new("1-2 3 4:5:6", "YEAR", "SECOND"),
+        }
+    }
+
+    #[test]
+    fn test_nested_struct_parses() {
+        validate_literal(&to_string(&outer()).unwrap()).unwrap();
+
+        let config = Config {
+            validate_output: true,
+            bigquery_float_format: true,
+            indent: Some(2),
+            ..Config::default()
+        };
+        to_string_with_config(&outer(), config).unwrap();
+    }
+
+    #[test]
+    fn test_malformed_output() {
+        let serialized = to_string(&outer()).unwrap();
+        // as if the closing parenthesis was never written
+        let truncated = &serialized[..serialized.len() - 1];
+        assert!(matches!(
+            validate_literal(truncated),
+            Err(Error::InvalidOutput(_))
+        ));
+        assert!(matches!(
+            validate_literal(&format!("{},1", serialized)),
+            Err(Error::InvalidOutput(_))
+        ));
+    }
+}
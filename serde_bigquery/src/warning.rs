@@ -0,0 +1,42 @@
+use std::fmt::{self, Display};
+
+/// Something the serializer changed in the output instead of failing.
+///
+/// `path` is the dotted path of the struct field the value was in (empty at the top level).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Warning {
+    /// An invalid value was written as `NULL` (`Config::best_effort`).
+    ReplacedUnsupportedWithNull { path: String, value: String },
+    /// A scalar was written as a string because of a `STRING` type hint (`Config::type_hints`).
+    CoercedToString { path: String },
+    /// A string was written as a `TIMESTAMP` (`Config::detect_timestamps`).
+    CoercedStringToTimestamp { path: String },
+}
+
+impl Warning {
+    pub fn path(&self) -> &str {
+        match self {
+            Warning::ReplacedUnsupportedWithNull { path, .. } => path,
+            Warning::CoercedToString { path } => path,
+            Warning::CoercedStringToTimestamp { path } => path,
+        }
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::ReplacedUnsupportedWithNull { value, .. } => formatter.write_fmt(
+                format_args!("unsupported value replaced with NULL: {}", value),
+            ),
+            Warning::CoercedToString { .. } => formatter.write_str("value coerced to STRING"),
+            Warning::CoercedStringToTimestamp { .. } => {
+                formatter.write_str("string coerced to TIMESTAMP")
+            }
+        }?;
+        if !self.path().is_empty() {
+            formatter.write_fmt(format_args!(" (at {})", self.path()))?;
+        }
+        Ok(())
+    }
+}
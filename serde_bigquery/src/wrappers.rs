@@ -0,0 +1,285 @@
+//! Wrappers for values that need a BigQuery specific representation.
+
+use std::borrow::Cow;
+
+use serde::{Serialize, Serializer};
+
+/// Name of the newtype struct `Json` serializes as.
+pub(crate) const JSON_TOKEN: &str = "$serde_bigquery::Json";
+/// Name of the newtype struct `Numeric` serializes as.
+pub(crate) const NUMERIC_TOKEN: &str = "$serde_bigquery::Numeric";
+/// Name of the newtype struct `BigNumeric` serializes as.
+pub(crate) const BIGNUMERIC_TOKEN: &str = "$serde_bigquery::BigNumeric";
+/// Name of the newtype struct `Date` serializes as.
+pub(crate) const DATE_TOKEN: &str = "$serde_bigquery::Date";
+/// Name of the newtype struct `Timestamp` serializes as.
+pub(crate) const TIMESTAMP_TOKEN: &str = "$serde_bigquery::Timestamp";
+/// Name of the newtype struct `Datetime` serializes as.
+pub(crate) const DATETIME_TOKEN: &str = "$serde_bigquery::Datetime";
+/// Name of the newtype struct `Geography` serializes as.
+pub(crate) const GEOGRAPHY_TOKEN: &str = "$serde_bigquery::Geography";
+/// Name of the newtype struct `Interval` serializes as.
+pub(crate) const INTERVAL_TOKEN: &str = "$serde_bigquery::Interval";
+
+/// Datetime parts an `Interval` range can start and end with, largest first.
+pub(crate) const INTERVAL_PARTS: &[&str] = &["YEAR", "MONTH", "DAY", "HOUR", "MINUTE", "SECOND"];
+
+/// Value of BigQuery `JSON` type holding raw JSON text, written as `JSON '...'` (see
+/// `Config::json_emit_mode` for the other forms).
+///
+/// The contents are written as they are, they're not parsed nor serialized as a struct.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Json<'a>(pub Cow<'a, str>);
+
+impl<'a> Json<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(json: S) -> Self {
+        Self(json.into())
+    }
+}
+
+/// Writes out the value as JSON text.
+#[cfg(feature = "serde_json")]
+impl From<&serde_json::Value> for Json<'static> {
+    fn from(value: &serde_json::Value) -> Self {
+        Self(Cow::Owned(value.to_string()))
+    }
+}
+
+impl Serialize for Json<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(JSON_TOKEN, self.0.as_ref())
+    }
+}
+
+/// Exact decimal written as `NUMERIC '123.45'`.
+///
+/// The string is validated when serialized: an optional sign, digits with an optional single
+/// decimal point and an optional exponent (`1.5e-3`). `NaN` or infinities aren't accepted.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Numeric<'a>(pub Cow<'a, str>);
+
+impl<'a> Numeric<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(decimal: S) -> Self {
+        Self(decimal.into())
+    }
+}
+
+impl Serialize for Numeric<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(NUMERIC_TOKEN, self.0.as_ref())
+    }
+}
+
+/// Exact decimal written as `BIGNUMERIC '123.45'`, validated like `Numeric`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BigNumeric<'a>(pub Cow<'a, str>);
+
+impl<'a> BigNumeric<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(decimal: S) -> Self {
+        Self(decimal.into())
+    }
+}
+
+impl Serialize for BigNumeric<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(BIGNUMERIC_TOKEN, self.0.as_ref())
+    }
+}
+
+/// Calendar date written as `DATE '2024-01-02'`.
+///
+/// The string is validated when serialized, it has to be exactly `YYYY-MM-DD`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Date<'a>(pub Cow<'a, str>);
+
+impl<'a> Date<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(date: S) -> Self {
+        Self(date.into())
+    }
+}
+
+impl Serialize for Date<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(DATE_TOKEN, self.0.as_ref())
+    }
+}
+
+/// Point in time written as `TIMESTAMP '2024-01-02 15:04:05+00'`.
+///
+/// The string is passed to BigQuery as it is, it's only checked not to be empty.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Timestamp<'a>(pub Cow<'a, str>);
+
+impl<'a> Timestamp<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(timestamp: S) -> Self {
+        Self(timestamp.into())
+    }
+}
+
+impl Serialize for Timestamp<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(TIMESTAMP_TOKEN, self.0.as_ref())
+    }
+}
+
+/// Civil date and time written as `DATETIME '2024-01-02 15:04:05'`, checked like `Timestamp`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Datetime<'a>(pub Cow<'a, str>);
+
+impl<'a> Datetime<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(datetime: S) -> Self {
+        Self(datetime.into())
+    }
+}
+
+impl Serialize for Datetime<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(DATETIME_TOKEN, self.0.as_ref())
+    }
+}
+
+/// Geography in WKT format written as `ST_GEOGFROMTEXT('POINT(1 2)')`.
+///
+/// The WKT is passed to BigQuery as it is, it's not parsed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Geography<'a>(pub Cow<'a, str>);
+
+impl<'a> Geography<'a> {
+    pub fn new<S: Into<Cow<'a, str>>>(wkt: S) -> Self {
+        Self(wkt.into())
+    }
+}
+
+impl Serialize for Geography<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(GEOGRAPHY_TOKEN, self.0.as_ref())
+    }
+}
+
+/// Duration written as `INTERVAL '1-2 3 4:5:6.789' YEAR TO SECOND`.
+///
+/// The range is validated when serialized: `from` and `to` have to be datetime parts (`YEAR`,
+/// `MONTH`, `DAY`, `HOUR`, `MINUTE` or `SECOND`) with `from` the larger one. The body is
+/// passed to BigQuery as it is.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Interval<'a> {
+    pub body: Cow<'a, str>,
+    pub from: Cow<'a, str>,
+    pub to: Cow<'a, str>,
+}
+
+impl<'a> Interval<'a> {
+    pub fn new<B, F, T>(body: B, from: F, to: T) -> Self
+    where
+        B: Into<Cow<'a, str>>,
+        F: Into<Cow<'a, str>>,
+        T: Into<Cow<'a, str>>,
+    {
+        Self {
+            body: body.into(),
+            from: from.into(),
+            to: to.into(),
+        }
+    }
+}
+
+impl Serialize for Interval<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let interval = (self.from.as_ref(), self.to.as_ref(), self.body.as_ref());
+        serializer.serialize_newtype_struct(INTERVAL_TOKEN, &interval)
+    }
+}
+
+/// Checks if `s` is a decimal BigQuery accepts in a `NUMERIC` literal: `[+-]digits[.digits]`
+/// (digits on at least one side of the dot) followed by an optional `e[+-]digits` exponent.
+pub(crate) fn is_decimal(s: &str) -> bool {
+    fn digits(s: &[u8]) -> (usize, &[u8]) {
+        let n = s.iter().take_while(|b| b.is_ascii_digit()).count();
+        (n, &s[n..])
+    }
+    fn sign(s: &[u8]) -> &[u8] {
+        match s {
+            [b'+' | b'-', rest @ ..] => rest,
+            _ => s,
+        }
+    }
+
+    let (integer_digits, rest) = digits(sign(s.as_bytes()));
+    let (fraction_digits, rest) = match rest {
+        [b'.', rest @ ..] => digits(rest),
+        _ => (0, rest),
+    };
+    if integer_digits + fraction_digits == 0 {
+        return false;
+    }
+    match rest {
+        [] => true,
+        [b'e' | b'E', rest @ ..] => matches!(digits(sign(rest)), (n, []) if n > 0),
+        _ => false,
+    }
+}
+
+/// Formats `s` as a single quoted string literal.
+///
+/// https://cloud.google.com/bigquery/docs/reference/standard-sql/lexical#string_and_bytes_literals
+pub(crate) fn single_quoted(s: &str) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('\'');
+    for c in s.chars() {
+        match c {
+            '\'' => result.push_str("\\'"),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            _ => result.push(c),
+        }
+    }
+    result.push('\'');
+    result
+}
+
+/// `s` as a double quoted string literal. Quotes, backslashes and control characters are
+/// always escaped, everything past ASCII only with `escape_unicode`.
+pub(crate) fn double_quoted(s: &str, escape_unicode: bool) -> String {
+    let mut result = String::with_capacity(s.len() + 2);
+    result.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if c.is_ascii_control() && c != '\x7f' => {
+                result.push_str(&format!("\\u{:04x}", c as u32))
+            }
+            c if escape_unicode && !c.is_ascii() => {
+                if (c as u32) > 0xffff {
+                    result.push_str(&format!("\\U{:08x}", c as u32))
+                } else {
+                    result.push_str(&format!("\\u{:04x}", c as u32))
+                }
+            }
+            _ => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decimals() {
+        for s in ["0", "-1", "+12.50", ".5", "5.", "1e10", "1.5E-3"] {
+            assert!(is_decimal(s), "{}", s);
+        }
+        for s in [
+            "", "NaN", "Infinity", "-inf", "1.2.3", ".", "-", "1e", "1e+", "1 ", "0x10",
+        ] {
+            assert!(!is_decimal(s), "{}", s);
+        }
+    }
+}